@@ -0,0 +1,54 @@
+use adrodb::Table;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rusqlite::Connection;
+
+const BATCH: usize = 1_000;
+
+fn set(c: &mut Criterion) {
+    let connection = Connection::open_in_memory().unwrap();
+    let table = Table::new("bench").allow_duplicate_keys();
+    let table = table.create(&connection).unwrap();
+
+    let mut group = c.benchmark_group("set");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("set", |b| {
+        b.iter(|| table.set(black_box("key"), black_box("value")).unwrap())
+    });
+    group.finish();
+}
+
+fn get(c: &mut Criterion) {
+    let connection = Connection::open_in_memory().unwrap();
+    let table = Table::new("bench");
+    let table = table.create(&connection).unwrap();
+    table.set("key", "value").unwrap();
+
+    let mut group = c.benchmark_group("get");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("get", |b| {
+        b.iter(|| table.get::<String>(black_box("key")).unwrap())
+    });
+    group.finish();
+}
+
+fn set_many(c: &mut Criterion) {
+    let connection = Connection::open_in_memory().unwrap();
+    let table = Table::new("bench").allow_duplicate_keys();
+    let table = table.create(&connection).unwrap();
+
+    let keys = (0..BATCH).map(|i| format!("key-{}", i)).collect::<Vec<_>>();
+    let items = keys
+        .iter()
+        .map(|key| (key.as_str(), "value"))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("set_many");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("set_many", |b| {
+        b.iter(|| table.set_many(black_box(&items)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, set, get, set_many);
+criterion_main!(benches);
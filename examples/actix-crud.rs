@@ -2,11 +2,11 @@ extern crate adrodb;
 
 use actix_web::{
     delete,
-    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    error::{ErrorBadRequest, ErrorNotFound},
     get, patch, post, web, App, Error, HttpResponse, HttpServer, Responder,
 };
-use adrodb::Table;
-use rusqlite::{Connection, Result};
+use adrodb::{SharedTable, Table};
+use rusqlite::Result;
 
 #[get("/")]
 async fn hello() -> impl Responder {
@@ -14,12 +14,13 @@ async fn hello() -> impl Responder {
 }
 
 #[post("/{key}/{value}")]
-async fn insert(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+async fn insert(
+    table: SharedTable,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, Error> {
     let (key, value) = path.into_inner();
-    let connection = Connection::open("./test.sqlite")
-        .map_err(|_| ErrorInternalServerError("Unable to connect to database"))?;
 
-    Table::existing("user_emails", &connection)
+    table
         .set(&key, &value)
         .map_err(|_| ErrorBadRequest("Unable to insert values"))?;
 
@@ -27,12 +28,10 @@ async fn insert(path: web::Path<(String, String)>) -> Result<HttpResponse, Error
 }
 
 #[get("/{key}")]
-async fn get(path: web::Path<String>) -> Result<HttpResponse, Error> {
+async fn get(table: SharedTable, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let key = path.into_inner();
-    let connection = Connection::open("./test.sqlite")
-        .map_err(|_| ErrorInternalServerError("Unable to connect to database"))?;
 
-    let value = Table::existing("user_emails", &connection)
+    let value = table
         .get::<String>(&key)
         .map_err(|_| ErrorNotFound("Value was not found"))?;
 
@@ -40,12 +39,10 @@ async fn get(path: web::Path<String>) -> Result<HttpResponse, Error> {
 }
 
 #[delete("/{key}")]
-async fn delete(path: web::Path<String>) -> Result<HttpResponse, Error> {
+async fn delete(table: SharedTable, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let key = path.into_inner();
-    let connection = Connection::open("./test.sqlite")
-        .map_err(|_| ErrorInternalServerError("Unable to connect to database"))?;
 
-    let value = Table::existing("user_emails", &connection)
+    let value = table
         .remove(&key)
         .map_err(|_| ErrorNotFound("Unable to delete by key"))?;
 
@@ -53,12 +50,13 @@ async fn delete(path: web::Path<String>) -> Result<HttpResponse, Error> {
 }
 
 #[patch("/{key}/{value}")]
-async fn update(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+async fn update(
+    table: SharedTable,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, Error> {
     let (key, value) = path.into_inner();
-    let connection = Connection::open("./test.sqlite")
-        .map_err(|_| ErrorInternalServerError("Unable to connect to database"))?;
 
-    let value = Table::existing("user_emails", &connection)
+    let value = table
         .update(&key, &value)
         .map_err(|_| ErrorNotFound("Unable to delete by key"))?;
 
@@ -67,16 +65,19 @@ async fn update(path: web::Path<(String, String)>) -> Result<HttpResponse, Error
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let conn = Connection::open("./test.sqlite");
-
-    // Creates the DB just in case
-    if let Ok(conn) = conn {
-        let table = Table::new("user_emails");
-        table.create(&conn).unwrap();
-    }
-
-    HttpServer::new(|| {
+    // One connection shared by every worker rather than one per request,
+    // creating the table just in case. Set `ADRODB_PATH` to use a
+    // different database file.
+    let connection = adrodb::open_default().expect("Unable to connect to database");
+    Table::new("user_emails")
+        .create(&connection)
+        .expect("Unable to create table");
+    // Handlers take the `SharedTable` straight from the app data
+    let table = SharedTable::new("user_emails", connection);
+
+    HttpServer::new(move || {
         App::new()
+            .app_data(table.data())
             .service(hello)
             .service(insert)
             .service(get)
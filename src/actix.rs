@@ -0,0 +1,61 @@
+use crate::SharedTable;
+use actix_web::{dev::Payload, error::ErrorInternalServerError, web, FromRequest, HttpRequest};
+use std::future::{ready, Ready};
+
+impl SharedTable {
+    /// Wraps a clone of the table in `web::Data` for `App::app_data`
+    ///
+    /// Registering the table this way lets handlers take a `SharedTable`
+    /// argument directly, see the `FromRequest` implementation. Requires
+    /// the `actix` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use actix_web::App;
+    /// use adrodb::SharedTable;
+    ///
+    /// let table = SharedTable::open(":memory:", "users")?;
+    /// let app = App::new().app_data(table.data());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn data(&self) -> web::Data<SharedTable> {
+        web::Data::new(self.clone())
+    }
+}
+
+/// Extracts the `SharedTable` registered with `App::app_data`
+///
+/// Handlers can take a `SharedTable` argument rather than
+/// `web::Data<SharedTable>`, the table is looked up from the app data set
+/// with `SharedTable::data` (or `web::Data::new`). Only one `SharedTable`
+/// can be registered per app this way, wrap tables in newtypes to share
+/// several. Responds with an internal server error if no table was
+/// registered. Requires the `actix` feature.
+///
+/// ### Example
+///
+/// ```
+/// use actix_web::{get, web, HttpResponse};
+/// use adrodb::SharedTable;
+///
+/// #[get("/{key}")]
+/// async fn get(table: SharedTable, key: web::Path<String>) -> HttpResponse {
+///     match table.get::<String>(&key) {
+///         Ok(value) => HttpResponse::Ok().body(value),
+///         Err(_) => HttpResponse::NotFound().finish(),
+///     }
+/// }
+/// ```
+impl FromRequest for SharedTable {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.app_data::<web::Data<SharedTable>>()
+                .map(|table| table.get_ref().clone())
+                .ok_or_else(|| ErrorInternalServerError("no SharedTable registered as app data")),
+        )
+    }
+}
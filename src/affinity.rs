@@ -0,0 +1,339 @@
+use crate::{table_not_found, transaction::Scope, DatabaseTable, VALUE_COLUMN};
+use rusqlite::{params, Error, Result};
+
+/// A SQLite column type affinity, see [`DatabaseTable::change_value_affinity`]
+///
+/// Affinity decides how SQLite converts values written to a column, see
+/// [Type Affinity](https://www.sqlite.org/datatype3.html#type_affinity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Text,
+    Numeric,
+    Integer,
+    Real,
+    Blob,
+}
+
+impl Affinity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Affinity::Text => "TEXT",
+            Affinity::Numeric => "NUMERIC",
+            Affinity::Integer => "INTEGER",
+            Affinity::Real => "REAL",
+            Affinity::Blob => "BLOB",
+        }
+    }
+}
+
+/// A column as described by `pragma_table_info`
+struct Column {
+    name: String,
+    declared_type: String,
+    not_null: bool,
+    default: Option<String>,
+    primary_key: i64,
+}
+
+/// Quotes a column name read from the schema
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Rebuilds the table with a different affinity on the value column
+    ///
+    /// Meant for legacy tables whose value column was declared e.g. `TEXT`
+    /// but should hold `BLOB`s. SQLite can't alter a column's type, so a
+    /// new table is created, the rows are copied across, the old table is
+    /// dropped and the new one renamed, all within one transaction. Column
+    /// types, `NOT NULL`, defaults, the primary key, `WITHOUT ROWID`,
+    /// rowids, indexes and triggers, including the temporary ones `watch`
+    /// creates, are kept, other constraints aren't.
+    /// Copied values are converted by the new affinity, so
+    /// `Affinity::Integer` turns the text `'5'` into the integer `5`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Affinity, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// connection.execute("CREATE TABLE users (k varchar(255) PRIMARY KEY, v TEXT)", ())?;
+    ///
+    /// let table = Table::existing("users", &connection);
+    /// table.change_value_affinity(Affinity::Blob)?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn change_value_affinity(&self, affinity: Affinity) -> Result<(), Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1)")?;
+        let columns = statement
+            .query_map(params![self.name], |row| {
+                Ok(Column {
+                    name: row.get(0)?,
+                    declared_type: row.get(1)?,
+                    not_null: row.get(2)?,
+                    default: row.get(3)?,
+                    primary_key: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<Column>, Error>>()?;
+        drop(statement);
+
+        if columns.is_empty() {
+            return Err(table_not_found(self.name));
+        }
+
+        let mut definitions = columns
+            .iter()
+            .map(|column| {
+                let declared_type = if column.name == VALUE_COLUMN {
+                    affinity.as_str()
+                } else {
+                    &column.declared_type
+                };
+                let mut definition = format!("{} {}", quote(&column.name), declared_type);
+                if column.not_null {
+                    definition.push_str(" NOT NULL");
+                }
+                if let Some(default) = &column.default {
+                    definition.push_str(&format!(" DEFAULT ({})", default));
+                }
+                definition
+            })
+            .collect::<Vec<_>>();
+
+        let mut primary_key = columns
+            .iter()
+            .filter(|column| column.primary_key > 0)
+            .collect::<Vec<_>>();
+        primary_key.sort_by_key(|column| column.primary_key);
+        if !primary_key.is_empty() {
+            definitions.push(format!(
+                "PRIMARY KEY ({})",
+                primary_key
+                    .iter()
+                    .map(|column| quote(&column.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let table_sql = self.connection.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![self.name],
+            |row| row.get::<usize, String>(0),
+        )?;
+        let mut statement = self.connection.prepare(
+            "SELECT sql FROM sqlite_master
+            WHERE type IN ('index', 'trigger') AND tbl_name = ?1 AND sql IS NOT NULL
+            UNION ALL
+            SELECT sql FROM sqlite_temp_master WHERE type = 'trigger' AND tbl_name = ?1",
+        )?;
+        let schema_sql = statement
+            .query_map(params![self.name], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, Error>>()?;
+        drop(statement);
+
+        let without_rowid = table_sql.to_ascii_uppercase().contains("WITHOUT ROWID");
+        let options = if without_rowid { " WITHOUT ROWID" } else { "" };
+        let mut names = columns
+            .iter()
+            .map(|column| quote(&column.name))
+            .collect::<Vec<_>>();
+        // Keeps rowids, which e.g. a `full_text_search` index refers to
+        if !without_rowid {
+            names.insert(0, "rowid".to_owned());
+        }
+        let names = names.join(", ");
+        let migrated = format!("{}_affinity_migration", self.name);
+
+        self.connection.execute_batch(&format!(
+            "CREATE TABLE {} ({}){};
+            INSERT INTO {} ({}) SELECT {} FROM {};
+            DROP TABLE {};
+            ALTER TABLE {} RENAME TO {};",
+            migrated,
+            definitions.join(", "),
+            options,
+            migrated,
+            names,
+            names,
+            self.name,
+            self.name,
+            migrated,
+            self.name
+        ))?;
+
+        for sql in schema_sql {
+            self.connection.execute_batch(&sql)?;
+        }
+
+        transaction.commit()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+
+    fn value_type(conn: &Connection, table: &str) -> String {
+        conn.query_row(
+            "SELECT type FROM pragma_table_info(?1) WHERE name = 'v'",
+            params![table],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_change_value_affinity_text_to_blob() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (
+                k varchar(255) PRIMARY KEY UNIQUE NOT NULL,
+                v TEXT,
+                created_at INTEGER NOT NULL DEFAULT (0),
+                updated_at INTEGER NOT NULL DEFAULT (0)
+            );
+            CREATE INDEX users_v ON users (v);",
+        )
+        .unwrap();
+
+        let db = Table::existing("users", &conn);
+        db.set("jimmy", "abc").unwrap();
+        db.set("bob", &vec![1u8, 2, 3]).unwrap();
+        // TEXT affinity stores numbers as text
+        db.set("sally", &5).unwrap();
+        assert_eq!("5", db.get::<String>("sally").unwrap());
+
+        db.change_value_affinity(Affinity::Blob).unwrap();
+
+        assert_eq!("BLOB", value_type(&conn, "users"));
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+        assert_eq!(vec![1u8, 2, 3], db.get::<Vec<u8>>("bob").unwrap());
+        assert_eq!("5", db.get::<String>("sally").unwrap());
+
+        // BLOB affinity stores values as given
+        db.update("sally", &5).unwrap();
+        assert_eq!(5, db.get::<i64>("sally").unwrap());
+
+        assert!(db.set("jimmy", "again").is_err());
+        let index: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'users_v'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(1, index);
+        assert!(db.verify_schema().is_ok());
+    }
+
+    #[test]
+    fn test_change_value_affinity_keeps_defaults() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users").without_rowid();
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "12").unwrap();
+        db.change_value_affinity(Affinity::Integer).unwrap();
+
+        assert_eq!("INTEGER", value_type(&conn, "users"));
+        assert!(db.describe().unwrap().ends_with("WITHOUT ROWID"));
+        assert_eq!(12, db.get::<i64>("jimmy").unwrap());
+
+        db.set("bob", "abc").unwrap();
+        let (created_at, updated_at): (i64, i64) = conn
+            .query_row(
+                "SELECT created_at, updated_at FROM users WHERE k = 'bob'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(created_at > 0 && updated_at > 0);
+    }
+
+    #[test]
+    fn test_change_value_affinity_keeps_rowids_and_triggers() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE audit (k TEXT);
+            CREATE TRIGGER users_audit AFTER INSERT ON users BEGIN
+                INSERT INTO audit (k) VALUES (new.k);
+            END;
+            INSERT INTO users (rowid, k, v) VALUES (7, 'jimmy', 'abc');",
+        )
+        .unwrap();
+
+        db.change_value_affinity(Affinity::Blob).unwrap();
+
+        assert_eq!(7, db.get_with_rowid::<String>("jimmy").unwrap().unwrap().0);
+        db.set("bob", "def").unwrap();
+        let audited: i64 = conn
+            .query_row("SELECT COUNT(*) FROM audit", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(2, audited);
+    }
+
+    #[cfg(feature = "fts")]
+    #[test]
+    fn test_change_value_affinity_keeps_search_in_sync() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+        db.set("a", "apples").unwrap();
+        db.set("b", "bananas").unwrap();
+        db.remove("a").unwrap();
+        db.set("c", "cherries").unwrap();
+
+        db.change_value_affinity(Affinity::Blob).unwrap();
+
+        assert_eq!("c", db.search("cherries").unwrap()[0].key);
+        db.update("c", "dates").unwrap();
+        assert!(db.search("cherries").unwrap().is_empty());
+        assert_eq!("c", db.search("dates").unwrap()[0].key);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_change_value_affinity_keeps_watch() {
+        use crate::Operation;
+        use std::sync::{Arc, Mutex};
+
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        db.watch(move |operation, key| seen.lock().unwrap().push((operation, key.to_owned())))
+            .unwrap();
+
+        db.change_value_affinity(Affinity::Blob).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        assert_eq!(
+            vec![(Operation::Insert, "jimmy".to_owned())],
+            *events.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_change_value_affinity_missing_table() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        assert!(Table::existing("users", &conn)
+            .change_value_affinity(Affinity::Blob)
+            .is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{transaction::Scope, DatabaseTable};
+use rusqlite::{Error, Result, ToSql};
+
+enum BatchOp {
+    Set(String, Box<dyn ToSql>),
+    Remove(String),
+}
+
+/// A queue of operations applied together in one transaction
+///
+/// Building a batch is decoupled from executing it, operations are only
+/// run against a table once `commit` is called. If any operation fails
+/// the whole batch is rolled back.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::{Batch, Table};
+/// use rusqlite::Connection;
+///
+/// let connection = Connection::open_in_memory()?;
+/// let table = Table::new("users");
+/// let table = table.create(&connection)?;
+///
+/// Batch::new()
+///     .set("jimmy", "abc")
+///     .set("bob", 123)
+///     .remove("jimmy")
+///     .commit(&table)?;
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Batch {
+    operations: Vec<BatchOp>,
+}
+
+impl Batch {
+    /// Creates a new empty batch
+    pub fn new() -> Self {
+        Batch::default()
+    }
+
+    /// Queues a `set` of the key & value
+    pub fn set<T: ToSql + 'static>(mut self, key: &str, value: T) -> Self {
+        self.operations
+            .push(BatchOp::Set(key.to_owned(), Box::new(value)));
+        self
+    }
+
+    /// Queues a `remove` of the key
+    pub fn remove(mut self, key: &str) -> Self {
+        self.operations.push(BatchOp::Remove(key.to_owned()));
+        self
+    }
+
+    /// Returns the number of queued operations
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns `true` if no operations have been queued
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Applies every queued operation in order within one transaction
+    ///
+    /// Returns the total number of affected rows. Nothing is written
+    /// if any of the operations fail.
+    pub fn commit(self, table: &DatabaseTable) -> Result<usize, Error> {
+        let transaction = Scope::begin(table.connection)?;
+        let mut affected = 0;
+
+        for operation in &self.operations {
+            affected += match operation {
+                BatchOp::Set(key, value) => table.set(key, value)?,
+                BatchOp::Remove(key) => table.remove(key)?,
+            };
+        }
+
+        transaction.commit()?;
+
+        Ok(affected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_batch_commit() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("existing", "value").unwrap();
+
+        let batch = Batch::new()
+            .set("jimmy", "abc")
+            .set("bob", 123)
+            .remove("existing")
+            .remove("jimmy");
+
+        assert_eq!(4, batch.len());
+
+        let result = batch.commit(&db);
+        assert_eq!(4, result.unwrap());
+
+        assert!(db.get::<String>("jimmy").is_err());
+        assert!(db.get::<String>("existing").is_err());
+        assert_eq!(123, db.get::<i32>("bob").unwrap());
+    }
+
+    #[test]
+    fn test_batch_rolls_back_on_failure() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let result = Batch::new().set("bob", 123).set("jimmy", "def").commit(&db);
+        assert!(result.is_err());
+
+        assert!(db.get::<i32>("bob").is_err());
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+    }
+}
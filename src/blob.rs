@@ -0,0 +1,103 @@
+use crate::{DatabaseTable, KEY_COLUMN, VALUE_COLUMN};
+use rusqlite::{blob::Blob, DatabaseName, Error, OptionalExtension, Result};
+
+impl<'a> DatabaseTable<'a> {
+    /// Opens an incremental I/O handle on a key's value
+    ///
+    /// Large binary values can be read and written in pieces rather than
+    /// loaded into memory at once. The returned
+    /// [`Blob`](https://docs.rs/rusqlite/latest/rusqlite/blob/struct.Blob.html)
+    /// implements `Read`, `Write` and `Seek`, starting at position `0`;
+    /// each read or write advances the position and `seek` moves it.
+    /// `read_at`/`write_at` read or write at an offset without moving it.
+    ///
+    /// A blob can't change size through the handle, writes past the end
+    /// fail, so reserve the space up front by storing a
+    /// `rusqlite::blob::ZeroBlob` of the final length. Fails with
+    /// `QueryReturnedNoRows` if the key is missing and the handle is
+    /// invalidated if the row is modified other than through it. Requires
+    /// the `blob` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::{blob::ZeroBlob, Connection};
+    /// use std::io::Write;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("files").create(&connection)?;
+    /// let table = Table::existing("files", &connection);
+    ///
+    /// table.set("avatar", &ZeroBlob(1024))?;
+    ///
+    /// let mut blob = table.open_blob("avatar")?;
+    /// blob.write_all(&[1, 2, 3]).unwrap();
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open_blob(&self, key: &str) -> Result<Blob<'a>, Error> {
+        let rowid = self
+            .connection
+            .query_row(
+                &format!(
+                    "SELECT rowid FROM {} WHERE {} = ?1 LIMIT 1",
+                    self.name, KEY_COLUMN
+                ),
+                [key],
+                |row| row.get::<usize, i64>(0),
+            )
+            .optional()?
+            .ok_or(Error::QueryReturnedNoRows)?;
+
+        self.connection
+            .blob_open(DatabaseName::Main, self.name, VALUE_COLUMN, rowid, false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::{blob::ZeroBlob, Connection, Error};
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_open_blob() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("files");
+        let db = table.create(&conn).unwrap();
+
+        let size = 1024 * 1024;
+        db.set("large", &ZeroBlob(size as i32)).unwrap();
+
+        let mut blob = db.open_blob("large").unwrap();
+        assert_eq!(size, blob.len());
+
+        let chunk = (0..=255).collect::<Vec<u8>>();
+        for _ in 0..size / chunk.len() {
+            blob.write_all(&chunk).unwrap();
+        }
+        assert!(blob.write_all(&chunk).is_err());
+
+        let mut slice = [0; 4];
+        blob.seek(SeekFrom::Start(254)).unwrap();
+        blob.read_exact(&mut slice).unwrap();
+        assert_eq!([254, 255, 0, 1], slice);
+        drop(blob);
+
+        let stored = db.get::<Vec<u8>>("large").unwrap();
+        assert_eq!(size, stored.len());
+        assert_eq!(&chunk[..], &stored[size - 256..]);
+    }
+
+    #[test]
+    fn test_open_blob_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("files");
+        let db = table.create(&conn).unwrap();
+
+        assert!(matches!(
+            db.open_blob("missing"),
+            Err(Error::QueryReturnedNoRows)
+        ));
+    }
+}
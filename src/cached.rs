@@ -0,0 +1,164 @@
+use crate::{value::from_value, DatabaseTable};
+use lru::LruCache;
+use rusqlite::{
+    types::{FromSql, Value},
+    Error, Result, ToSql,
+};
+use std::{cell::Cell, cell::RefCell, num::NonZeroUsize};
+
+/// A `DatabaseTable` with an in-process LRU cache in front of reads
+///
+/// `get` checks the cache before querying SQLite, keeping up to `capacity`
+/// of the most recently read values in memory. Writes made through the
+/// `CachedTable` invalidate the cached value, writes made elsewhere (e.g.
+/// another connection) aren't seen until the value is evicted.
+/// Requires the `lru` feature.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::{CachedTable, Table};
+/// use rusqlite::Connection;
+/// use std::num::NonZeroUsize;
+///
+/// let connection = Connection::open_in_memory()?;
+/// let table = Table::new("users");
+/// let table = table.create(&connection)?;
+/// let cached = CachedTable::new(table, NonZeroUsize::new(100).unwrap());
+///
+/// cached.set("jimmy", "abc")?;
+///
+/// let value = cached.get::<String>("jimmy")?;
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub struct CachedTable<'a> {
+    table: DatabaseTable<'a>,
+    cache: RefCell<LruCache<String, Value>>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+impl<'a> CachedTable<'a> {
+    /// Wraps a table with a cache holding up to `capacity` values
+    pub fn new(table: DatabaseTable<'a>, capacity: NonZeroUsize) -> Self {
+        CachedTable {
+            table,
+            cache: RefCell::new(LruCache::new(capacity)),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    /// Returns the wrapped table
+    ///
+    /// Writes made directly through the table bypass cache invalidation.
+    pub fn table(&self) -> &DatabaseTable<'a> {
+        &self.table
+    }
+
+    /// Get a value by key, reading from the cache when possible
+    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        if let Some(value) = self.cache.borrow_mut().get(key) {
+            self.hits.set(self.hits.get() + 1);
+            return from_value(value);
+        }
+
+        self.misses.set(self.misses.get() + 1);
+
+        let value = self.table.get::<Value>(key)?;
+        let result = from_value(&value);
+        self.cache.borrow_mut().put(key.to_owned(), value);
+
+        result
+    }
+
+    /// Inserts some data into the table, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.cache.borrow_mut().pop(key);
+        self.table.set(key, value)
+    }
+
+    /// Updates some data in the table, see `DatabaseTable::update`
+    pub fn update<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.cache.borrow_mut().pop(key);
+        self.table.update(key, value)
+    }
+
+    /// Removes data by the key, see `DatabaseTable::remove`
+    pub fn remove(&self, key: &str) -> Result<usize, Error> {
+        self.cache.borrow_mut().pop(key);
+        self.table.remove(key)
+    }
+
+    /// Returns how many reads were served from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.get()
+    }
+
+    /// Returns how many reads had to query the database
+    pub fn misses(&self) -> u64 {
+        self.misses.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_cached_get() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let cached = CachedTable::new(db, NonZeroUsize::new(2).unwrap());
+
+        cached.set("jimmy", "abc").unwrap();
+
+        assert_eq!("abc", cached.get::<String>("jimmy").unwrap());
+        assert_eq!((0, 1), (cached.hits(), cached.misses()));
+
+        // Change the value behind the cache's back, the cached value is returned
+        conn.execute("UPDATE users SET v = 'changed'", ()).unwrap();
+
+        assert_eq!("abc", cached.get::<String>("jimmy").unwrap());
+        assert_eq!((1, 1), (cached.hits(), cached.misses()));
+    }
+
+    #[test]
+    fn test_cached_invalidation() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let cached = CachedTable::new(db, NonZeroUsize::new(2).unwrap());
+
+        cached.set("jimmy", &1).unwrap();
+        assert_eq!(1, cached.get::<i32>("jimmy").unwrap());
+
+        cached.update("jimmy", &2).unwrap();
+        assert_eq!(2, cached.get::<i32>("jimmy").unwrap());
+
+        cached.remove("jimmy").unwrap();
+        assert!(cached.get::<i32>("jimmy").is_err());
+
+        assert_eq!((0, 3), (cached.hits(), cached.misses()));
+    }
+
+    #[test]
+    fn test_cached_eviction() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let cached = CachedTable::new(db, NonZeroUsize::new(1).unwrap());
+
+        cached.set("a", &1).unwrap();
+        cached.set("b", &2).unwrap();
+
+        cached.get::<i32>("a").unwrap();
+        cached.get::<i32>("b").unwrap();
+        cached.get::<i32>("a").unwrap();
+
+        assert_eq!((0, 3), (cached.hits(), cached.misses()));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{sanitize_identifier, Table, CREATED_AT_COLUMN, NOW, UPDATED_AT_COLUMN, VALUE_COLUMN};
+use rusqlite::{ffi, params_from_iter, types::FromSql, Connection, Error, Result, ToSql};
+
+/// A table keyed by several columns, see `Table::with_composite_key`
+#[derive(Debug)]
+pub struct CompositeTable<'a> {
+    name: &'a str,
+    columns: &'a [&'a str],
+}
+
+/// Operations on a table created by `CompositeTable::create`
+///
+/// Keys are given as a slice with one part per key column, in the order
+/// the columns were declared. Passing the wrong number of parts fails with
+/// `Error::InvalidParameterCount`.
+#[derive(Debug)]
+pub struct CompositeDatabaseTable<'a> {
+    name: &'a str,
+    columns: &'a [&'a str],
+    connection: &'a Connection,
+}
+
+impl<'a> Table<'a> {
+    /// Creates a table keyed by several columns
+    ///
+    /// For natural keys made of several parts, such as `(tenant, user)`,
+    /// each part gets its own column and together they form the table's
+    /// `PRIMARY KEY`. Column names must pass `sanitize_identifier` and
+    /// can't clash with the value or timestamp columns.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let members = Table::with_composite_key("members", &["tenant", "user"]);
+    /// let members = members.create(&connection)?;
+    ///
+    /// members.set(&["acme", "jimmy"], "admin")?;
+    ///
+    /// assert_eq!("admin", members.get::<String>(&["acme", "jimmy"])?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn with_composite_key(name: &'a str, columns: &'a [&'a str]) -> CompositeTable<'a> {
+        CompositeTable { name, columns }
+    }
+}
+
+impl<'a> CompositeTable<'a> {
+    /// Creates the table in your database if it doesn't already exist
+    pub fn create(&self, connection: &'a Connection) -> Result<CompositeDatabaseTable<'a>, Error> {
+        sanitize_identifier(self.name)?;
+
+        if self.columns.is_empty() {
+            return Err(invalid_columns("a composite key needs at least one column"));
+        }
+
+        for column in self.columns {
+            sanitize_identifier(column)?;
+
+            if [VALUE_COLUMN, CREATED_AT_COLUMN, UPDATED_AT_COLUMN].contains(column) {
+                return Err(invalid_columns(&format!(
+                    "key column {:?} clashes with a reserved column",
+                    column
+                )));
+            }
+        }
+
+        let key_columns = self
+            .columns
+            .iter()
+            .map(|column| format!("{} varchar(255) NOT NULL,", column))
+            .collect::<Vec<String>>()
+            .join("\n                ");
+
+        connection.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                {}
+                {},
+                {} INTEGER NOT NULL DEFAULT ({}),
+                {} INTEGER NOT NULL DEFAULT ({}),
+                PRIMARY KEY ({})
+            )",
+                self.name,
+                key_columns,
+                VALUE_COLUMN,
+                CREATED_AT_COLUMN,
+                NOW,
+                UPDATED_AT_COLUMN,
+                NOW,
+                self.columns.join(", ")
+            ),
+            (),
+        )?;
+
+        Ok(CompositeDatabaseTable {
+            name: self.name,
+            columns: self.columns,
+            connection,
+        })
+    }
+}
+
+impl<'a> CompositeDatabaseTable<'a> {
+    /// Inserts a value under a composite key, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: &[&str], value: &T) -> Result<usize, Error> {
+        self.check_key(key)?;
+
+        let placeholders = (1..=self.columns.len() + 1)
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut params = key
+            .iter()
+            .map(|part| part as &dyn ToSql)
+            .collect::<Vec<_>>();
+        params.push(&value as &dyn ToSql);
+
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} ({}, {}) VALUES({})",
+                self.name,
+                self.columns.join(", "),
+                VALUE_COLUMN,
+                placeholders
+            ),
+            params_from_iter(params),
+        )
+    }
+
+    /// Get a value by its composite key, see `DatabaseTable::get`
+    pub fn get<T: FromSql>(&self, key: &[&str]) -> Result<T, Error> {
+        self.check_key(key)?;
+
+        self.connection.query_row(
+            &format!(
+                "SELECT {} FROM {} WHERE {} LIMIT 1",
+                VALUE_COLUMN,
+                self.name,
+                self.key_condition(1)
+            ),
+            params_from_iter(key),
+            |row| row.get::<usize, T>(0),
+        )
+    }
+
+    /// Update a value by its composite key, see `DatabaseTable::update`
+    pub fn update<T: ToSql + ?Sized>(&self, key: &[&str], value: &T) -> Result<usize, Error> {
+        self.check_key(key)?;
+
+        let mut params = vec![&value as &dyn ToSql];
+        params.extend(key.iter().map(|part| part as &dyn ToSql));
+
+        self.connection.execute(
+            &format!(
+                "UPDATE {} SET {} = ?1, {} = {} WHERE {}",
+                self.name,
+                VALUE_COLUMN,
+                UPDATED_AT_COLUMN,
+                NOW,
+                self.key_condition(2)
+            ),
+            params_from_iter(params),
+        )
+    }
+
+    /// Remove a value by its composite key, see `DatabaseTable::remove`
+    pub fn remove(&self, key: &[&str]) -> Result<usize, Error> {
+        self.check_key(key)?;
+
+        self.connection.execute(
+            &format!("DELETE FROM {} WHERE {}", self.name, self.key_condition(1)),
+            params_from_iter(key),
+        )
+    }
+
+    /// Errors unless there is one key part per key column
+    fn check_key(&self, key: &[&str]) -> Result<(), Error> {
+        if key.len() != self.columns.len() {
+            return Err(Error::InvalidParameterCount(key.len(), self.columns.len()));
+        }
+
+        Ok(())
+    }
+
+    /// SQL condition matching every key column, numbering parameters from
+    /// `first`
+    fn key_condition(&self, first: usize) -> String {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!("{} = ?{}", column, first + i))
+            .collect::<Vec<String>>()
+            .join(" AND ")
+    }
+}
+
+fn invalid_columns(message: &str) -> Error {
+    Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_ERROR), Some(message.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::OptionalExtension;
+
+    #[test]
+    fn test_composite_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::with_composite_key("members", &["tenant", "user"]);
+        let db = table.create(&conn).unwrap();
+
+        db.set(&["acme", "jimmy"], "admin").unwrap();
+        db.set(&["acme", "bob"], "member").unwrap();
+        db.set(&["globex", "jimmy"], "owner").unwrap();
+
+        assert_eq!("admin", db.get::<String>(&["acme", "jimmy"]).unwrap());
+        assert_eq!("owner", db.get::<String>(&["globex", "jimmy"]).unwrap());
+
+        // The pair is the primary key
+        assert!(db.set(&["acme", "jimmy"], "again").is_err());
+
+        db.update(&["acme", "bob"], "admin").unwrap();
+        assert_eq!("admin", db.get::<String>(&["acme", "bob"]).unwrap());
+
+        assert_eq!(1, db.remove(&["acme", "jimmy"]).unwrap());
+        assert_eq!(
+            None,
+            db.get::<String>(&["acme", "jimmy"]).optional().unwrap()
+        );
+        assert_eq!("owner", db.get::<String>(&["globex", "jimmy"]).unwrap());
+    }
+
+    #[test]
+    fn test_composite_key_part_count() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::with_composite_key("members", &["tenant", "user"]);
+        let db = table.create(&conn).unwrap();
+
+        assert!(matches!(
+            db.set(&["acme"], "admin"),
+            Err(Error::InvalidParameterCount(1, 2))
+        ));
+        assert!(db.get::<String>(&["acme", "jimmy", "extra"]).is_err());
+    }
+
+    #[test]
+    fn test_composite_key_invalid_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        assert!(Table::with_composite_key("members", &[])
+            .create(&conn)
+            .is_err());
+        assert!(Table::with_composite_key("members", &["tenant", "v"])
+            .create(&conn)
+            .is_err());
+        assert!(Table::with_composite_key("members", &["tenant; DROP"])
+            .create(&conn)
+            .is_err());
+    }
+}
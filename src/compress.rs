@@ -0,0 +1,352 @@
+use crate::{
+    table_columns, transaction::Scope, DatabaseTable, Table, KEY_COLUMN, NOW, UPDATED_AT_COLUMN,
+    VALUE_COLUMN,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rusqlite::{
+    functions::FunctionFlags,
+    params,
+    types::{ToSqlOutput, Type, Value, ValueRef},
+    Error, Result, ToSql,
+};
+use std::io::{Read, Write};
+
+/// Column tagging how each value is stored
+pub(crate) const COMPRESSED_COLUMN: &str = "compressed";
+
+/// The value is stored as is
+const RAW: i64 = 0;
+/// The value is gzip compressed text
+const GZIP_TEXT: i64 = 1;
+/// The value is a gzip compressed blob
+const GZIP_BLOB: i64 = 2;
+
+/// SQL function readers restore values with, see `decompress_sql`
+const DECOMPRESS_FUNCTION: &str = "adrodb_decompress";
+
+impl<'a> Table<'a> {
+    /// Gzip compresses values larger than `bytes` when they are stored
+    ///
+    /// Text and blob values longer than the threshold are compressed by
+    /// `set`, `update`, `append`, `set_many` & `set_iter`, smaller values
+    /// and numbers are stored as is so tiny values don't pay for
+    /// compression. Each row is tagged in an extra `compressed` column so
+    /// readers know whether to decompress.
+    ///
+    /// Any handle to a table with the `compressed` column decompresses
+    /// the values it reads, including one from `existing`. Only the
+    /// `DatabaseTable` returned by `create` knows the threshold, other
+    /// handles store values as is. The column is only added when the
+    /// table is first created, a table created without this option lacks
+    /// it. Requires the `compression` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("pages").compress_above(1024);
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set("home", &"<html>".repeat(1000))?;
+    ///
+    /// let page = table.get::<String>("home")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn compress_above(mut self, bytes: usize) -> Self {
+        self.compress_above = Some(bytes);
+        self
+    }
+}
+
+/// Compresses a value when it is text or a blob larger than `threshold`
+///
+/// Returns the compressed bytes and their tag, or `None` when the value
+/// should be stored as is.
+fn compress<T: ToSql + ?Sized>(value: &T, threshold: usize) -> Result<Option<(Vec<u8>, i64)>> {
+    let output = value.to_sql()?;
+    let value = match &output {
+        ToSqlOutput::Borrowed(value) => *value,
+        ToSqlOutput::Owned(value) => ValueRef::from(value),
+        _ => return Ok(None),
+    };
+
+    let (bytes, tag) = match value {
+        ValueRef::Text(text) => (text, GZIP_TEXT),
+        ValueRef::Blob(blob) => (blob, GZIP_BLOB),
+        _ => return Ok(None),
+    };
+
+    if bytes.len() <= threshold {
+        return Ok(None);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .and_then(|_| encoder.finish())
+        .map(|compressed| Some((compressed, tag)))
+        .map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Restores a value stored with the given tag
+fn decompress(value: Value, tag: i64) -> Result<Value> {
+    let compressed = match (value, tag) {
+        (value, RAW) => return Ok(value),
+        (Value::Blob(compressed), GZIP_TEXT | GZIP_BLOB) => compressed,
+        (value, _) => {
+            return Err(Error::InvalidColumnType(
+                0,
+                VALUE_COLUMN.to_owned(),
+                value.data_type(),
+            ))
+        }
+    };
+
+    let mut bytes = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))?;
+
+    if tag == GZIP_BLOB {
+        return Ok(Value::Blob(bytes));
+    }
+
+    String::from_utf8(bytes)
+        .map(Value::Text)
+        .map_err(|e| Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+/// The expression selecting a decompressed value
+///
+/// Calls the function `is_compressed` registers on the connection.
+pub(crate) fn decompress_sql() -> String {
+    format!(
+        "{}({}, {})",
+        DECOMPRESS_FUNCTION, VALUE_COLUMN, COMPRESSED_COLUMN
+    )
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Whether the table has the `compressed` column
+    ///
+    /// Read from the schema once per handle. Registers the function
+    /// `decompress_sql` calls when it does.
+    pub(crate) fn is_compressed(&self) -> Result<bool, Error> {
+        if let Some(compressed) = self.compressed.get() {
+            return Ok(compressed);
+        }
+
+        let compressed = table_columns(self.name, self.connection)?
+            .iter()
+            .any(|column| column == COMPRESSED_COLUMN);
+        if compressed {
+            self.connection.create_scalar_function(
+                DECOMPRESS_FUNCTION,
+                2,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |ctx| decompress(ctx.get::<Value>(0)?, ctx.get::<i64>(1)?),
+            )?;
+        }
+        self.compressed.set(Some(compressed));
+
+        Ok(compressed)
+    }
+
+    /// `set` for tables created with `compress_above`
+    pub(crate) fn set_compressed<T: ToSql + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        threshold: usize,
+    ) -> Result<usize, Error> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "INSERT INTO {} ({}, {}, {}) VALUES(?1, ?2, ?3)",
+            self.name, KEY_COLUMN, VALUE_COLUMN, COMPRESSED_COLUMN
+        ))?;
+
+        match compress(value, threshold)? {
+            Some((compressed, tag)) => statement.execute(params![key, compressed, tag]),
+            None => statement.execute(params![key, value, RAW]),
+        }
+    }
+
+    /// `set_many` & `set_iter` for tables created with `compress_above`
+    pub(crate) fn set_iter_compressed<K, T, I>(
+        &self,
+        items: I,
+        threshold: usize,
+    ) -> Result<usize, Error>
+    where
+        K: AsRef<str>,
+        T: ToSql,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let transaction = Scope::begin(self.connection)?;
+        let mut inserted = 0;
+
+        for (key, value) in items {
+            inserted += self.set_compressed(key.as_ref(), &value, threshold)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(inserted)
+    }
+
+    /// `update` for tables created with `compress_above`
+    pub(crate) fn update_compressed<T: ToSql + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        threshold: usize,
+    ) -> Result<usize, Error> {
+        let sql = format!(
+            "UPDATE {} SET {} = ?1, {} = ?2, {} = {} WHERE {} = ?3",
+            self.name, VALUE_COLUMN, COMPRESSED_COLUMN, UPDATED_AT_COLUMN, NOW, KEY_COLUMN
+        );
+
+        match compress(value, threshold)? {
+            Some((compressed, tag)) => self.connection.execute(&sql, params![compressed, tag, key]),
+            None => self.connection.execute(&sql, params![value, RAW, key]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn stored(conn: &Connection, key: &str) -> (String, i64) {
+        conn.query_row(
+            "SELECT typeof(v), compressed FROM pages WHERE k = ?1",
+            [key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_small_value_stored_raw() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        db.set("small", "hello").unwrap();
+        db.set("number", &42).unwrap();
+
+        assert_eq!(("text".to_owned(), RAW), stored(&conn, "small"));
+        assert_eq!("hello", db.get::<String>("small").unwrap());
+        assert_eq!(42, db.get::<i64>("number").unwrap());
+    }
+
+    #[test]
+    fn test_large_value_stored_compressed() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        let text = "<p>hello</p>".repeat(100);
+        let bytes = vec![7u8; 1000];
+        db.set("text", &text).unwrap();
+        db.set("bytes", &bytes).unwrap();
+
+        assert_eq!(("blob".to_owned(), GZIP_TEXT), stored(&conn, "text"));
+        assert_eq!(("blob".to_owned(), GZIP_BLOB), stored(&conn, "bytes"));
+
+        let length: i64 = conn
+            .query_row("SELECT length(v) FROM pages WHERE k = 'text'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(length < text.len() as i64);
+
+        assert_eq!(text, db.get::<String>("text").unwrap());
+        assert_eq!(bytes, db.get::<Vec<u8>>("bytes").unwrap());
+    }
+
+    #[test]
+    fn test_update_switches_storage() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        let large = "x".repeat(1000);
+        db.set("page", "small").unwrap();
+        db.update("page", &large).unwrap();
+
+        assert_eq!(GZIP_TEXT, stored(&conn, "page").1);
+        assert_eq!(large, db.get::<String>("page").unwrap());
+
+        db.update("page", "small again").unwrap();
+
+        assert_eq!(("text".to_owned(), RAW), stored(&conn, "page"));
+        assert_eq!("small again", db.get::<String>("page").unwrap());
+    }
+
+    #[test]
+    fn test_bulk_writers_compress() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        let large = "x".repeat(1000);
+        db.set_many(&[("many", large.as_str()), ("small", "hello")])
+            .unwrap();
+        db.set_iter(vec![("iter", large.clone())]).unwrap();
+        db.append("appended", &large).unwrap();
+
+        for key in ["many", "iter", "appended"] {
+            assert_eq!(GZIP_TEXT, stored(&conn, key).1);
+            assert_eq!(large, db.get::<String>(key).unwrap());
+        }
+        assert_eq!(("text".to_owned(), RAW), stored(&conn, "small"));
+    }
+
+    #[test]
+    fn test_other_handles_read_compressed() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        let large = "x".repeat(1000);
+        let bytes = vec![7u8; 1000];
+        db.set("text", &large).unwrap();
+        db.set("bytes", &bytes).unwrap();
+
+        let existing = Table::existing("pages", &conn);
+        assert_eq!(large, existing.get::<String>("text").unwrap());
+        assert_eq!(bytes, existing.get::<Vec<u8>>("bytes").unwrap());
+        assert_eq!(
+            large,
+            existing.get_many::<String>(&["text"]).unwrap()[0].value
+        );
+        assert_eq!(large, db.range::<String>("t", "u").unwrap()[0].value);
+
+        // Existing handles store values as is
+        existing.update("text", "small").unwrap();
+        assert_eq!(("text".to_owned(), RAW), stored(&conn, "text"));
+        assert_eq!("small", db.get::<String>("text").unwrap());
+        assert_eq!(
+            Some(bytes),
+            existing.remove_returning::<Vec<u8>>("bytes").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_remove_if_compares_decompressed() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("pages").compress_above(64);
+        let db = table.create(&conn).unwrap();
+
+        let large = "x".repeat(1000);
+        db.set("page", &large).unwrap();
+
+        assert!(!db.remove_if("page", "small").unwrap());
+        assert!(db.remove_if("page", &large).unwrap());
+        assert!(db.keys().unwrap().is_empty());
+    }
+}
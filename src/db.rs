@@ -1,19 +1,154 @@
-use rusqlite::{params, types::FromSql, Connection, Error, Result, ToSql};
+use rusqlite::{
+    ffi, params, params_from_iter,
+    types::{FromSql, Value},
+    Connection, Error, ErrorCode, OptionalExtension, Params, Result, Row, ToSql,
+};
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
+    fmt,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-type RusqilteResponse = Result<usize, Error>;
+use crate::{transaction::Scope, ConnectionFactory};
 
-const KEY_COLUMN: &str = "k";
-const VALUE_COLUMN: &str = "v";
+type RusqliteResponse = Result<usize, Error>;
+
+pub(crate) const KEY_COLUMN: &str = "k";
+pub(crate) const VALUE_COLUMN: &str = "v";
+pub(crate) const CREATED_AT_COLUMN: &str = "created_at";
+pub(crate) const UPDATED_AT_COLUMN: &str = "updated_at";
+
+/// The current on-disk format of tables created by adrodb
+///
+/// - `1`: key & value columns
+/// - `2`: adds `created_at` & `updated_at` timestamp columns
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Number of rows `keys_iter` and `iter_prefix` fetch at a time
+const KEYS_ITER_CHUNK_SIZE: usize = 100;
+
+/// Number of keys bound per statement by `set_many`, `get_many` & `remove_many`
+///
+/// Larger batches are split into chunks of this size so a statement never
+/// binds more than SQLite's default limit of 999 parameters, even with a
+/// value alongside each key.
+pub const BATCH_CHUNK_SIZE: usize = 400;
+
+/// SQLite expression for the current time as seconds since the unix epoch
+pub(crate) const NOW: &str = "strftime('%s', 'now')";
 
-#[derive(Debug)]
 pub struct Table<'a> {
-    name: &'a str,
+    pub(crate) name: &'a str,
+    duplicate_keys: bool,
+    synchronous: Option<Synchronous>,
+    cache_size: Option<i64>,
+    mmap_size: Option<i64>,
+    without_rowid: bool,
+    pub(crate) track_access: bool,
+    pub(crate) expiring: bool,
+    pub(crate) connection_factory: Option<Box<dyn ConnectionFactory + 'a>>,
+    #[cfg(feature = "compression")]
+    pub(crate) compress_above: Option<usize>,
+    #[cfg(feature = "fts")]
+    pub(crate) full_text: bool,
+}
+
+impl fmt::Debug for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Table");
+        debug
+            .field("name", &self.name)
+            .field("duplicate_keys", &self.duplicate_keys)
+            .field("synchronous", &self.synchronous)
+            .field("cache_size", &self.cache_size)
+            .field("mmap_size", &self.mmap_size)
+            .field("without_rowid", &self.without_rowid)
+            .field("track_access", &self.track_access)
+            .field("expiring", &self.expiring)
+            .field("connection_factory", &self.connection_factory.is_some());
+        #[cfg(feature = "compression")]
+        debug.field("compress_above", &self.compress_above);
+        #[cfg(feature = "fts")]
+        debug.field("full_text", &self.full_text);
+        debug.finish()
+    }
 }
 
-#[derive(Debug)]
+/// The `PRAGMA synchronous` setting, see [`Table::synchronous`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+            Synchronous::Extra => "EXTRA",
+        }
+    }
+}
+
+/// A handle to a table that has been created
+///
+/// `set`, `get`, `update` and `remove` use the connection's prepared
+/// statement cache so repeated calls reuse the same compiled statement
+/// rather than preparing it again. The cache size can be tuned with
+/// `Connection::set_prepared_statement_cache_capacity`.
+#[derive(Debug, Clone)]
 pub struct DatabaseTable<'a> {
-    name: &'a str,
-    connection: &'a Connection,
+    pub(crate) name: &'a str,
+    pub(crate) connection: &'a Connection,
+    pub(crate) track_access: Cell<Option<bool>>,
+    #[cfg(feature = "compression")]
+    pub(crate) compress_above: Option<usize>,
+    #[cfg(feature = "compression")]
+    pub(crate) compressed: Cell<Option<bool>>,
+}
+
+/// A key & value pair returned by listing methods such as `page_after`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValue<T> {
+    pub key: String,
+    pub value: T,
+}
+
+impl<T> From<(String, T)> for KeyValue<T> {
+    fn from((key, value): (String, T)) -> Self {
+        KeyValue { key, value }
+    }
+}
+
+impl<T> From<KeyValue<T>> for (String, T) {
+    fn from(entry: KeyValue<T>) -> Self {
+        (entry.key, entry.value)
+    }
+}
+
+impl<T: FromSql> KeyValue<T> {
+    /// Reads a key & value from the first two columns of a row
+    pub(crate) fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(KeyValue {
+            key: row.get::<usize, String>(0)?,
+            value: row.get::<usize, T>(1)?,
+        })
+    }
+}
+
+/// A value read as one of two types, see `DatabaseTable::get_as`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Either<T, U> {
+    Left(T),
+    Right(U),
 }
 
 impl<'a> Table<'a> {
@@ -30,7 +165,188 @@ impl<'a> Table<'a> {
     /// let users_table = Table::new("users");
     /// ```
     pub fn new(name: &'a str) -> Self {
-        Table { name }
+        Table {
+            name,
+            duplicate_keys: false,
+            synchronous: None,
+            cache_size: None,
+            mmap_size: None,
+            without_rowid: false,
+            track_access: false,
+            expiring: false,
+            connection_factory: None,
+            #[cfg(feature = "compression")]
+            compress_above: None,
+            #[cfg(feature = "fts")]
+            full_text: false,
+        }
+    }
+
+    /// Creates a new Table Struct, validating the name
+    ///
+    /// Like `new` but checks the name with `sanitize_identifier` up front,
+    /// returning an error for an invalid name rather than only failing
+    /// once the table is used. Prefer it for names that come from users or
+    /// config, `new` stays the infallible path for trusted names.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    ///
+    /// let users_table = Table::try_new("users")?;
+    ///
+    /// assert!(Table::try_new("users; DROP TABLE users").is_err());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn try_new(name: &'a str) -> Result<Self, Error> {
+        Ok(Table::new(sanitize_identifier(name)?))
+    }
+
+    /// Allows the same key to be stored more than once
+    ///
+    /// Creates the table without the `PRIMARY KEY` & `UNIQUE` constraints
+    /// on the key column, useful for append-only logs where each `set`
+    /// adds a new row rather than failing on an existing key. Use
+    /// `get_all_for_key` to read every value of a key, `get` returns the
+    /// first match. `update` and `remove` apply to every row of a key.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("events").allow_duplicate_keys();
+    /// let events = table.create(&connection)?;
+    ///
+    /// events.set("jimmy", "signed up")?;
+    /// events.set("jimmy", "logged in")?;
+    ///
+    /// assert_eq!(2, events.get_all_for_key::<String>("jimmy")?.len());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn allow_duplicate_keys(mut self) -> Self {
+        self.duplicate_keys = true;
+        self
+    }
+
+    /// Sets `PRAGMA synchronous` on the connection when the table is created
+    ///
+    /// Controls how often SQLite waits for data to reach the disk. `Full`
+    /// is SQLite's default, `Normal` is safe in WAL mode and faster.
+    ///
+    /// **DANGER:** with `Off` SQLite hands writes to the operating system
+    /// without waiting, a crash or power loss can lose recent transactions
+    /// or corrupt the database. Only use it for data you can rebuild.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Synchronous, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("users")
+    ///     .synchronous(Synchronous::Normal)
+    ///     .cache_size(-64_000)
+    ///     .mmap_size(268_435_456);
+    ///
+    /// table.create(&connection)?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.synchronous = Some(synchronous);
+        self
+    }
+
+    /// Sets `PRAGMA cache_size` on the connection when the table is created
+    ///
+    /// Positive values are a number of pages, negative values are a size
+    /// in KiB, e.g. `-64_000` for roughly 64MB of page cache.
+    pub fn cache_size(mut self, cache_size: i64) -> Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Sets `PRAGMA mmap_size` on the connection when the table is created
+    ///
+    /// The maximum number of bytes of the database file to memory map,
+    /// `0` disables memory mapped I/O.
+    pub fn mmap_size(mut self, mmap_size: i64) -> Self {
+        self.mmap_size = Some(mmap_size);
+        self
+    }
+
+    /// Creates the table as a `WITHOUT ROWID` table
+    ///
+    /// Rows are stored in a B-tree keyed by the key column itself rather
+    /// than a hidden rowid, which is usually smaller and faster for key
+    /// value workloads. SQLite requires a non-null primary key for this,
+    /// which the key column already is, so it can't be combined with
+    /// `allow_duplicate_keys`. Methods relying on rowids, such as
+    /// `get_with_rowid` and `watch`, don't work with these tables.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("users").without_rowid();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn without_rowid(mut self) -> Self {
+        self.without_rowid = true;
+        self
+    }
+
+    /// Sets the `ConnectionFactory` used when the builder opens a database
+    ///
+    /// Every connection opened through `open` passes through the factory,
+    /// centralising setup such as loading extensions or custom pragmas.
+    /// A closure taking `&Connection` runs as setup on a connection opened
+    /// as normal.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let owned = Table::new("users")
+    ///     .connection_factory(|connection: &Connection| {
+    ///         connection.pragma_update(None, "foreign_keys", true)
+    ///     })
+    ///     .open(":memory:")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn connection_factory(mut self, factory: impl ConnectionFactory + 'a) -> Self {
+        self.connection_factory = Some(Box::new(factory));
+        self
+    }
+
+    /// Applies the configured pragmas to a connection
+    fn apply_pragmas(&self, connection: &Connection) -> Result<(), Error> {
+        if let Some(synchronous) = self.synchronous {
+            connection.pragma_update(None, "synchronous", synchronous.as_str())?;
+        }
+
+        if let Some(cache_size) = self.cache_size {
+            connection.pragma_update(None, "cache_size", cache_size)?;
+        }
+
+        if let Some(mmap_size) = self.mmap_size {
+            // May return the new size as a row, which `pragma_update` rejects
+            connection.execute_batch(&format!("PRAGMA mmap_size = {}", mmap_size))?;
+        }
+
+        Ok(())
     }
 
     /// Creates the table in your database
@@ -39,7 +355,12 @@ impl<'a> Table<'a> {
     /// the table if it doesn't already exist with the appropriate
     /// key & value columns. The key column is treated as the primary
     /// key, must be unique and can't be null. The value column remains
-    /// unknown for flexibility.
+    /// unknown for flexibility. Each row also tracks `created_at` and
+    /// `updated_at` timestamps, stored as seconds since the unix epoch.
+    /// An existing table made before the timestamps were added gets them
+    /// through `migrate_from_v1`. The table name must pass
+    /// `sanitize_identifier`. Any pragmas set on the builder are applied
+    /// to the connection first.
     ///
     /// This returns a instance of `DatabaseTable` which is used to
     /// perform operations on your newly created table
@@ -54,24 +375,109 @@ impl<'a> Table<'a> {
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
+    /// let connection = Connection::open_in_memory()?;
     /// let users_table = Table::new("users");
     ///
     /// users_table.create(&connection)?;
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
-    pub fn create(&'a self, connection: &'a Connection) -> Result<DatabaseTable, Error> {
-        connection.execute(
-            &format!(
-                "CREATE TABLE IF NOT EXISTS {} (
-                {} varchar(255) PRIMARY KEY UNIQUE NOT NULL,
-                {}
-            )",
-                self.name, KEY_COLUMN, VALUE_COLUMN
-            ),
-            (),
-        )?;
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn create(&self, connection: &'a Connection) -> Result<DatabaseTable<'a>, Error> {
+        sanitize_identifier(self.name)?;
+        self.apply_pragmas(connection)?;
+
+        connection.execute(&self.create_sql(), ())?;
+        if Table::is_compatible(self.name, connection)? {
+            self.migrate_from_v1(connection)?;
+        }
+        #[cfg(feature = "fts")]
+        if self.full_text {
+            crate::fts::create_index(self.name, connection)?;
+        }
+
+        let mut table = DatabaseTable::new(self.name, connection);
+        if self.track_access {
+            table.track_access = Cell::new(Some(true));
+        }
+        #[cfg(feature = "compression")]
+        {
+            table.compress_above = self.compress_above;
+        }
+
+        Ok(table)
+    }
+
+    /// Returns the `CREATE TABLE` statement `create` would run
+    ///
+    /// Builds the SQL without a connection, respecting builder options such
+    /// as `allow_duplicate_keys`, which is useful for generating migration
+    /// files. Pragmas aren't part of the statement and the table name isn't
+    /// validated here, pass it through `sanitize_identifier` before running
+    /// the SQL yourself.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    ///
+    /// let sql = Table::new("users").create_sql();
+    ///
+    /// assert!(sql.starts_with("CREATE TABLE IF NOT EXISTS users ("));
+    /// ```
+    pub fn create_sql(&self) -> String {
+        let key_constraints = if self.duplicate_keys {
+            "NOT NULL"
+        } else {
+            "PRIMARY KEY UNIQUE NOT NULL"
+        };
 
-        Ok(DatabaseTable::new(&self.name, &connection))
+        let mut extra_columns = String::new();
+        if self.track_access {
+            extra_columns.push_str(&format!(
+                ",\n    {} INTEGER NOT NULL DEFAULT 0,\n    {} INTEGER NOT NULL DEFAULT ({})",
+                crate::eviction::ACCESS_COUNT_COLUMN,
+                crate::eviction::ACCESSED_AT_COLUMN,
+                NOW
+            ));
+        }
+        if self.expiring {
+            extra_columns.push_str(&format!(
+                ",\n    {} INTEGER",
+                crate::expiry::EXPIRES_AT_COLUMN
+            ));
+        }
+        #[cfg(feature = "compression")]
+        if self.compress_above.is_some() {
+            extra_columns.push_str(&format!(
+                ",\n    {} INTEGER NOT NULL DEFAULT 0",
+                crate::compress::COMPRESSED_COLUMN
+            ));
+        }
+
+        let options = if self.without_rowid {
+            " WITHOUT ROWID"
+        } else {
+            ""
+        };
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+    {} varchar(255) {},
+    {},
+    {} INTEGER NOT NULL DEFAULT ({}),
+    {} INTEGER NOT NULL DEFAULT ({}){}
+){}",
+            self.name,
+            KEY_COLUMN,
+            key_constraints,
+            VALUE_COLUMN,
+            CREATED_AT_COLUMN,
+            NOW,
+            UPDATED_AT_COLUMN,
+            NOW,
+            extra_columns,
+            options
+        )
     }
 
     /// Returns a instance of `DatabaseTable` without running a `CREATE` command
@@ -82,41 +488,69 @@ impl<'a> Table<'a> {
     /// **DANGER:** if your table doesn't exist, every action performed on the
     /// `DatabaseTable` will fail.
     ///
+    /// A `Transaction` or `Savepoint` derefs to its `Connection`, so passing
+    /// `&transaction` (or `&*transaction` where the type must be spelled
+    /// out) runs every operation as part of it. Helpers taking a
+    /// `&Connection` work both inside and outside a transaction, methods
+    /// that need their own transaction, such as `set_many`, open a
+    /// savepoint when one is already running.
+    ///
     /// ### Example
     ///
     /// ```
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
-    /// let table = Table::as_existing("users", &connection);
+    /// let mut connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc@abc.com")?;
     ///
-    /// table.insert(...)
+    /// let transaction = connection.transaction()?;
+    /// Table::existing("users", &*transaction).set("bob", "def@def.com")?;
+    /// transaction.commit()?;
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
     pub fn existing(name: &'a str, connection: &'a Connection) -> DatabaseTable<'a> {
-        DatabaseTable::new(&name, &connection)
+        DatabaseTable::new(name, connection)
     }
-}
 
-impl<'a> DatabaseTable<'a> {
-    /// Creates a new instance of the Database table with a reference to
-    /// a connection
+    /// Returns a instance of `DatabaseTable` if the table exists
     ///
-    /// Operations to interact with the data is tied to this struct to ensure
-    /// the user of the API is sure that the table has been created
-    /// previously.
+    /// A strict alternative to `existing` that checks the table is present
+    /// and has an adrodb compatible schema (see `is_compatible`) before
+    /// returning it. A missing or foreign table is reported up front as a
+    /// `no such table` error rather than on the first operation.
     ///
-    /// This is a private method with is called from `Table`.
-    fn new(name: &'a str, connection: &'a Connection) -> Self {
-        DatabaseTable { name, connection }
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    ///
+    /// assert!(Table::require("users", &connection).is_err());
+    ///
+    /// Table::new("users").create(&connection)?;
+    /// let table = Table::require("users", &connection)?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn require(name: &'a str, connection: &'a Connection) -> Result<DatabaseTable<'a>, Error> {
+        if !Table::is_compatible(name, connection)? {
+            return Err(table_not_found(name));
+        }
+
+        Ok(DatabaseTable::new(name, connection))
     }
 
-    /// Inserts some data into the table
+    /// Returns a instance of `DatabaseTable`, creating the table if needed
     ///
-    /// This will insert a key & value into the given table. The key
-    /// is a string slice while the value needs to adhere to the
-    /// [ToSql](https://docs.rs/rusqlite/latest/rusqlite/trait.ToSql.html) trait
-    /// provided by rusqlite.
+    /// A safer alternative to `existing` that runs `CREATE TABLE IF NOT EXISTS`
+    /// before returning the `DatabaseTable`. This removes the footgun of
+    /// every action failing when the table hasn't been created yet, at the
+    /// cost of a `CREATE` statement when the table does exist.
     ///
     /// ### Example
     ///
@@ -124,30 +558,24 @@ impl<'a> DatabaseTable<'a> {
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
-    /// let users_table = Table::new("users");
-    ///
-    /// users_table.create(&connection)?;
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::existing_or_create("users", &connection)?;
     ///
-    /// users_table.insert("jimmy", "abc@abc.com")?;
+    /// table.set("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
-    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> RusqilteResponse {
-        let result = self.connection.execute(
-            &format!(
-                "INSERT INTO {} ({}, {}) VALUES(?1, ?2);",
-                self.name, KEY_COLUMN, VALUE_COLUMN
-            ),
-            params![key, value],
-        )?;
-
-        Ok(result)
+    pub fn existing_or_create(
+        name: &'a str,
+        connection: &'a Connection,
+    ) -> Result<DatabaseTable<'a>, Error> {
+        Table::new(name).create(connection)
     }
 
-    /// Get a value in the database by key
+    /// Checks the database for corruption
     ///
-    /// Given a key a query will be made to attempt to get a
-    /// value from the database of type `T`. Failure could mean
-    /// the SQL query failed, casting value or no results were found
+    /// Runs `PRAGMA integrity_check` against the whole database and returns
+    /// `true` if SQLite reports `ok`. Any other report means the database is
+    /// corrupt, allowing servers to fail fast on startup.
     ///
     /// ### Example
     ///
@@ -155,53 +583,52 @@ impl<'a> DatabaseTable<'a> {
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
-    /// let table = Table::existing("users");
-    ///
-    /// table.insert("jimmy", "abc")?;
+    /// let connection = Connection::open_in_memory()?;
     ///
-    /// let result = table.get::<String>("jimmy");
+    /// assert!(Table::integrity_check(&connection)?);
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
-    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
-        let mut statement = self.connection.prepare(&format!(
-            "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
-            VALUE_COLUMN, self.name, KEY_COLUMN
-        ))?;
-        let value = statement.query_row(params![key], |row| row.get::<usize, T>(0))?;
+    pub fn integrity_check(connection: &Connection) -> Result<bool, Error> {
+        let mut statement = connection.prepare("PRAGMA integrity_check")?;
+        let reports = statement
+            .query_map([], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, Error>>()?;
 
-        Ok(value)
+        Ok(reports == ["ok"])
     }
 
-    /// Removes data by the key
+    /// Writes a compacted copy of the whole database to a new file
     ///
-    /// Removes data from the table based on a given key. The request
-    /// returns the number of affected rows. If the key is missing
-    /// from the table the result will be `Ok(usize)`.
+    /// Uses SQLite's `VACUUM INTO`, which copies every table into `path`
+    /// without the free pages left behind by deleted rows, leaving the
+    /// original database untouched. Works for in-memory databases and
+    /// while other connections are reading, handy for periodic backups of
+    /// a busy database. Fails if `path` already exists and isn't empty.
     ///
     /// ### Example
-    /// ```
+    ///
+    /// ```no_run
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
-    /// let table = Table::existing("users");
+    /// let connection = Connection::open("./data.sqlite")?;
     ///
-    /// let result = table.remove("jimmy");
+    /// Table::vacuum_into(&connection, "./data-backup.sqlite")?;
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
-    pub fn remove(&self, key: &str) -> RusqilteResponse {
-        let result = self.connection.execute(
-            &format!("DELETE FROM {} WHERE {} = ?1", self.name, KEY_COLUMN),
-            params![key],
-        )?;
-        Ok(result)
+    pub fn vacuum_into<P: AsRef<Path>>(connection: &Connection, path: P) -> Result<(), Error> {
+        let path = path.as_ref().to_string_lossy();
+        connection.execute("VACUUM INTO ?1", params![path])?;
+
+        Ok(())
     }
 
-    /// Updates some data in the table
+    /// Checks a table exists and has an adrodb compatible schema
     ///
-    /// This will update value based on the provided key in a given table. The key
-    /// is a string slice while the value needs to adhere to the
-    /// [ToSql](https://docs.rs/rusqlite/latest/rusqlite/trait.ToSql.html) trait
-    /// provided by rusqlite.
+    /// Inspects the table's columns to confirm it has a `k` column and a
+    /// `v` column. Returns `false` if the table is missing
+    /// or if it is a foreign table sharing the same name, which prevents
+    /// operating on it through `existing`.
     ///
     /// ### Example
     ///
@@ -209,194 +636,4537 @@ impl<'a> DatabaseTable<'a> {
     /// use adrodb::Table;
     /// use rusqlite::Connection;
     ///
-    /// let connection = Connection::open("./test.sqlite")?;
-    /// let users_table = Table::new("users");
-    ///
-    /// // Create & Set
+    /// let connection = Connection::open_in_memory()?;
     ///
-    /// users_table.update("jimmy", "abc@abc.com")?;
+    /// if Table::is_compatible("users", &connection)? {
+    ///     let table = Table::existing("users", &connection);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
     /// ```
-    pub fn update<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> RusqilteResponse {
-        let result = self.connection.execute(
-            &format!(
-                "UPDATE {} SET {} = ?1 WHERE {} = ?2",
-                self.name, VALUE_COLUMN, KEY_COLUMN
-            ),
-            params![value, key],
-        )?;
+    pub fn is_compatible(name: &str, connection: &Connection) -> Result<bool, Error> {
+        let columns = table_columns(name, connection)?;
 
-        Ok(result)
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
+        let has_key = columns.iter().any(|column| column == KEY_COLUMN);
+        let has_value = columns.iter().any(|column| column == VALUE_COLUMN);
 
-    #[test]
-    fn test_table_new() {
-        let table = Table::new("test");
-        assert_eq!(table.name, "test");
+        Ok(has_key && has_value)
     }
 
-    #[test]
-    fn test_table_create() -> Result<(), Error> {
-        let connection = Connection::open_in_memory()?;
-        let table = Table::new("test");
+    /// Removes every row from several tables at once
+    ///
+    /// Every name is validated with `sanitize_identifier` before anything
+    /// is deleted, then the tables are cleared within one transaction so
+    /// either all or none of them are emptied. Virtual tables such as a
+    /// `full_text_search` index are skipped, the index is cleared along
+    /// with its table. Returns the total number of removed rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// # Table::new("sessions").create(&connection)?;
+    ///
+    /// let removed = Table::clear_all(&connection, &["users", "sessions"])?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn clear_all(connection: &Connection, names: &[&str]) -> Result<usize, Error> {
+        for name in names {
+            sanitize_identifier(name)?;
+        }
 
-        // Create the table
-        let result = table.create(&connection);
+        let transaction = Scope::begin(connection)?;
+        let mut removed = 0;
 
-        assert_eq!(true, result.is_ok());
+        for name in names {
+            // A full-text index is kept in sync by its table's triggers
+            if matches!(
+                table_type(name, connection)?.as_deref(),
+                Some("virtual" | "shadow")
+            ) {
+                continue;
+            }
 
-        // To test that, we attempt to drop the same table.
-        // If it wasn't created, this will return a Err
-        let result = connection.execute("DROP TABLE test", ());
+            removed += transaction.execute(&format!("DELETE FROM {}", name), ())?;
+        }
 
-        assert_eq!(true, result.is_ok());
+        transaction.commit()?;
 
-        Ok(())
+        Ok(removed)
     }
 
-    #[test]
-    fn test_table_insert() -> Result<(), Error> {
-        let conn = Connection::open_in_memory()?;
-        let table = Table::new("test");
-        let table = table.create(&conn)?;
+    /// Drops every adrodb table in the database
+    ///
+    /// Intended for test teardown. Tables are found through
+    /// `pragma_table_list` and only ordinary tables passing
+    /// `is_compatible` with a name accepted by `sanitize_identifier` are
+    /// dropped, so other tables sharing the database are left alone. A
+    /// `full_text_search` index is dropped with its table. Every table is
+    /// dropped within one transaction. Returns the number of dropped
+    /// tables.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// Table::new("users").create(&connection)?;
+    /// Table::new("sessions").create(&connection)?;
+    ///
+    /// assert_eq!(2, Table::drop_all(&connection)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn drop_all(connection: &Connection) -> Result<usize, Error> {
+        let transaction = Scope::begin(connection)?;
+        let names = transaction
+            .prepare("SELECT name FROM pragma_table_list WHERE schema = 'main' AND type = 'table'")?
+            .query_map([], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, Error>>()?;
+        let mut dropped = 0;
 
-        let result = table.set("jimmy", "abc@abc.com");
-        assert_eq!(true, result.is_ok());
+        for name in names {
+            if sanitize_identifier(&name).is_err() || !Table::is_compatible(&name, connection)? {
+                continue;
+            }
 
-        let mut stmt = conn.prepare(&format!("SELECT * from {}", table.name))?;
-        let mut rows = stmt.query([])?;
-        let first = rows.next()?;
+            transaction.execute(&format!("DROP TABLE {}", name), ())?;
+            #[cfg(feature = "fts")]
+            crate::fts::drop_index(&name, connection)?;
+            dropped += 1;
+        }
 
-        assert_eq!(true, first.is_some());
-        let key: String = first.unwrap().get(0)?;
-        assert_eq!("jimmy".to_owned(), key);
+        transaction.commit()?;
 
-        Ok(())
+        Ok(dropped)
     }
 
-    #[test]
-    fn test_existing_table() {
-        let conn = Connection::open_in_memory().unwrap();
-        let table = Table::existing("nope", &conn);
-        let result = table.set("key", "value");
-
-        assert_eq!(true, result.is_err());
-
-        let table = Table::new("users");
-        table.create(&conn).unwrap();
-        let table = Table::existing("users", &conn).set("key1", "value");
-
-        assert_eq!(true, table.is_ok());
+    /// Reports the adrodb format version of the table
+    ///
+    /// The version is derived from the table's columns so it always
+    /// reflects the actual on-disk layout, see `FORMAT_VERSION` for the
+    /// list of versions. Tables made by `create` report the current
+    /// `FORMAT_VERSION`. Errors if the table is missing or isn't an
+    /// adrodb table.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Table, FORMAT_VERSION};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("users");
+    /// table.create(&connection)?;
+    ///
+    /// assert_eq!(FORMAT_VERSION, table.format_version(&connection)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn format_version(&self, connection: &Connection) -> Result<u32, Error> {
+        if !Table::is_compatible(self.name, connection)? {
+            return Err(table_not_found(self.name));
+        }
+
+        let columns = table_columns(self.name, connection)?;
+        let has_column = |name: &str| columns.iter().any(|column| column == name);
+
+        if has_column(CREATED_AT_COLUMN) && has_column(UPDATED_AT_COLUMN) {
+            Ok(2)
+        } else {
+            Ok(1)
+        }
+    }
+
+    /// Upgrades a format version 1 table to the current format
+    ///
+    /// Adds the `created_at` & `updated_at` columns missing from tables
+    /// made by older versions of adrodb with `ALTER TABLE ADD COLUMN`, so
+    /// rows, rowids, other columns, indexes and triggers are untouched.
+    /// Existing rows are stamped with the time of the migration. `ALTER
+    /// TABLE` can't add a column defaulting to the current time, so the
+    /// new columns default to `0` and an `AFTER INSERT` trigger named
+    /// `{name}_timestamps` stamps rows as they are inserted. Only missing
+    /// columns are added, so it's safe to call on every start up.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Table, FORMAT_VERSION};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// connection.execute("CREATE TABLE users (k varchar(255) PRIMARY KEY, v)", ())?;
+    ///
+    /// let table = Table::new("users");
+    /// table.migrate_from_v1(&connection)?;
+    ///
+    /// assert_eq!(FORMAT_VERSION, table.format_version(&connection)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn migrate_from_v1(&self, connection: &Connection) -> Result<(), Error> {
+        if self.format_version(connection)? >= 2 {
+            return Ok(());
+        }
+
+        let transaction = Scope::begin(connection)?;
+        self.add_timestamp_columns(&transaction)?;
+        transaction.commit()
+    }
+
+    /// Adds whichever of the `created_at` & `updated_at` columns are missing
+    fn add_timestamp_columns(&self, connection: &Connection) -> Result<(), Error> {
+        let existing = table_columns(self.name, connection)?;
+
+        for column in [CREATED_AT_COLUMN, UPDATED_AT_COLUMN] {
+            if existing.iter().any(|existing| existing == column) {
+                continue;
+            }
+
+            connection.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} INTEGER NOT NULL DEFAULT 0",
+                    self.name, column
+                ),
+                (),
+            )?;
+            connection.execute(
+                &format!(
+                    "UPDATE {} SET {} = {} WHERE {} = 0",
+                    self.name, column, NOW, column
+                ),
+                (),
+            )?;
+        }
+
+        connection.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {name}_timestamps AFTER INSERT ON {name}
+            WHEN new.{created_at} = 0 OR new.{updated_at} = 0 BEGIN
+                UPDATE {name} SET
+                    {created_at} = CASE {created_at} WHEN 0 THEN {now} ELSE {created_at} END,
+                    {updated_at} = CASE {updated_at} WHEN 0 THEN {now} ELSE {updated_at} END
+                WHERE {k} = new.{k};
+            END;",
+            name = self.name,
+            k = KEY_COLUMN,
+            created_at = CREATED_AT_COLUMN,
+            updated_at = UPDATED_AT_COLUMN,
+            now = NOW
+        ))
+    }
+}
+
+/// Environment variable read by `open_default` for the database path
+pub const PATH_ENV_VAR: &str = "ADRODB_PATH";
+
+/// Database path used by `open_default` when `ADRODB_PATH` isn't set
+pub const DEFAULT_PATH: &str = "./test.sqlite";
+
+/// Opens the database configured by the environment
+///
+/// Reads the path from the `ADRODB_PATH` environment variable, falling
+/// back to `./test.sqlite`, so binaries can point at a different database
+/// without recompiling.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::Table;
+///
+/// # std::env::set_var("ADRODB_PATH", ":memory:");
+/// let connection = adrodb::open_default()?;
+/// let table = Table::new("users").create(&connection)?;
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn open_default() -> Result<Connection, Error> {
+    Connection::open(path_from(std::env::var_os(PATH_ENV_VAR)))
+}
+
+/// The database path for a value of `ADRODB_PATH`, if it's set
+fn path_from(value: Option<OsString>) -> OsString {
+    value.unwrap_or_else(|| DEFAULT_PATH.into())
+}
+
+/// Validates a table or column name
+///
+/// adrodb interpolates table names into its SQL, so names are limited to
+/// ASCII letters, digits and underscores, must not start with a digit and
+/// must not use SQLite's reserved `sqlite_` prefix. Anything else, such as
+/// quotes, semicolons, whitespace or unicode, is rejected to prevent SQL
+/// injection. Returns the name unchanged when it is valid.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::sanitize_identifier;
+///
+/// assert_eq!("users", sanitize_identifier("users")?);
+/// assert!(sanitize_identifier("users; DROP TABLE users").is_err());
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn sanitize_identifier(name: &str) -> Result<&str, Error> {
+    let valid = match name.chars().next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.to_ascii_lowercase().starts_with("sqlite_")
+        }
+        None => false,
+    };
+
+    if !valid {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!("invalid identifier: {:?}", name)),
+        ));
+    }
+
+    Ok(name)
+}
+
+/// The error SQLite itself returns when a table is missing
+pub(crate) fn table_not_found(name: &str) -> Error {
+    Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_ERROR),
+        Some(format!("no such table: {}", name)),
+    )
+}
+
+/// SQL condition matching keys starting with the `?1` parameter
+///
+/// Unlike `LIKE` this is case sensitive and doesn't treat `%` or `_` in
+/// the prefix as wildcards.
+fn prefix_condition() -> String {
+    format!("substr({}, 1, length(?1)) = ?1", KEY_COLUMN)
+}
+
+/// Escapes `%`, `_` and `\` so a `LIKE ... ESCAPE '\'` pattern matches
+/// them literally
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Returns the type of a table as listed by `pragma_table_list`
+///
+/// `table`, `view`, `virtual` or `shadow`, the internal tables of a
+/// virtual table. A missing table has no type.
+pub(crate) fn table_type(name: &str, connection: &Connection) -> Result<Option<String>, Error> {
+    connection
+        .query_row(
+            "SELECT type FROM pragma_table_list WHERE schema = 'main' AND name = ?1",
+            params![name],
+            |row| row.get::<usize, String>(0),
+        )
+        .optional()
+}
+
+/// Returns the column names of a table
+///
+/// A missing table has no columns.
+pub(crate) fn table_columns(name: &str, connection: &Connection) -> Result<Vec<String>, Error> {
+    let mut statement = connection.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = statement
+        .query_map(params![name], |row| row.get::<usize, String>(0))?
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    Ok(columns)
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Creates a new instance of the Database table with a reference to
+    /// a connection
+    ///
+    /// Operations to interact with the data is tied to this struct to ensure
+    /// the user of the API is sure that the table has been created
+    /// previously.
+    ///
+    /// This is a private method with is called from `Table`.
+    fn new(name: &'a str, connection: &'a Connection) -> Self {
+        DatabaseTable {
+            name,
+            connection,
+            track_access: Cell::new(None),
+            #[cfg(feature = "compression")]
+            compress_above: None,
+            #[cfg(feature = "compression")]
+            compressed: Cell::new(None),
+        }
+    }
+
+    /// The expression readers select the value column with
+    ///
+    /// Decompresses the values of tables with a `compressed` column.
+    pub(crate) fn value_sql(&self) -> Result<String, Error> {
+        #[cfg(feature = "compression")]
+        if self.is_compressed()? {
+            return Ok(crate::compress::decompress_sql());
+        }
+
+        Ok(VALUE_COLUMN.to_owned())
+    }
+
+    /// A copy of this table's options for a table with another name
+    fn with_name(&self, name: &'a str) -> Self {
+        DatabaseTable {
+            name,
+            ..self.clone()
+        }
+    }
+
+    /// Checks the table exists with the key & value columns adrodb expects
+    ///
+    /// The runtime counterpart to `Table::is_compatible` for a handle
+    /// that's already been obtained. Calling this straight after `existing`
+    /// turns the confusing failures of a missing or foreign table into an
+    /// immediate error naming the problem. The table must have a `NOT NULL`
+    /// `k` column and a `v` column, extra columns are ignored.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.verify_schema()?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn verify_schema(&self) -> Result<(), Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, \"notnull\" FROM pragma_table_info(?1)")?;
+        let columns = statement
+            .query_map(params![self.name], |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, bool>(1)?))
+            })?
+            .collect::<Result<Vec<(String, bool)>, Error>>()?;
+
+        if columns.is_empty() {
+            return Err(table_not_found(self.name));
+        }
+
+        let problem = match (
+            columns.iter().find(|(name, _)| name == KEY_COLUMN),
+            columns.iter().any(|(name, _)| name == VALUE_COLUMN),
+        ) {
+            (None, _) => format!("missing key column {}", KEY_COLUMN),
+            (Some((_, false)), _) => format!("key column {} allows NULL", KEY_COLUMN),
+            (_, false) => format!("missing value column {}", VALUE_COLUMN),
+            _ => return Ok(()),
+        };
+
+        Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!("incompatible table {}: {}", self.name, problem)),
+        ))
+    }
+
+    /// Inserts some data into the table
+    ///
+    /// This will insert a key & value into the given table. The key
+    /// is a string slice while the value needs to adhere to the
+    /// [ToSql](https://docs.rs/rusqlite/latest/rusqlite/trait.ToSql.html) trait
+    /// provided by rusqlite.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let users_table = Table::new("users");
+    ///
+    /// let users_table = users_table.create(&connection)?;
+    ///
+    /// users_table.set("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value), fields(table = self.name)))]
+    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> RusqliteResponse {
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            return self.set_compressed(key, value, threshold);
+        }
+
+        let mut statement = self.connection.prepare_cached(&format!(
+            "INSERT INTO {} ({}, {}) VALUES(?1, ?2);",
+            self.name, KEY_COLUMN, VALUE_COLUMN
+        ))?;
+        let result = statement.execute(params![key, value])?;
+
+        Ok(result)
+    }
+
+    /// Get a value in the database by key
+    ///
+    /// Given a key a query will be made to attempt to get a
+    /// value from the database of type `T`. Failure could mean
+    /// the SQL query failed, casting value or no results were found
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// let result = table.get::<String>("jimmy");
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        let value = self.read(key)?;
+
+        if self.tracks_access()? {
+            self.record_access(key)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Reads a value for `get` without recording the access
+    ///
+    /// Used by methods reading a value only to write it back.
+    pub(crate) fn read<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, self.name, KEY_COLUMN
+        ))?;
+        let value = statement.query_row(params![key], |row| row.get::<usize, T>(0))?;
+
+        Ok(value)
+    }
+
+    /// Checks whether a key is in the table
+    ///
+    /// Cheaper than `get` as the value is never read or converted.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// assert!(table.contains_key("jimmy")?);
+    /// assert!(!table.contains_key("bob")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn contains_key(&self, key: &str) -> Result<bool, Error> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT EXISTS(SELECT 1 FROM {} WHERE {} = ?1)",
+            self.name, KEY_COLUMN
+        ))?;
+
+        statement.query_row(params![key], |row| row.get::<usize, bool>(0))
+    }
+
+    /// Checks whether a key is in the table
+    ///
+    /// An alias for `contains_key`.
+    pub fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.contains_key(key)
+    }
+
+    /// Removes data by the key
+    ///
+    /// Removes data from the table based on a given key. The request
+    /// returns the number of affected rows. If the key is missing
+    /// from the table the result will be `Ok(usize)`.
+    ///
+    /// ### Example
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let result = table.remove("jimmy");
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn remove(&self, key: &str) -> RusqliteResponse {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "DELETE FROM {} WHERE {} = ?1",
+            self.name, KEY_COLUMN
+        ))?;
+        let result = statement.execute(params![key])?;
+        Ok(result)
+    }
+
+    /// Updates some data in the table
+    ///
+    /// This will update value based on the provided key in a given table and
+    /// bump its `updated_at` timestamp. The key
+    /// is a string slice while the value needs to adhere to the
+    /// [ToSql](https://docs.rs/rusqlite/latest/rusqlite/trait.ToSql.html) trait
+    /// provided by rusqlite.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let users_table = Table::new("users");
+    ///
+    /// // Create & Set
+    /// # let users_table = users_table.create(&connection)?;
+    ///
+    /// users_table.update("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value), fields(table = self.name)))]
+    pub fn update<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> RusqliteResponse {
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            return self.update_compressed(key, value, threshold);
+        }
+        #[cfg(feature = "compression")]
+        if self.is_compressed()? {
+            // Stores the value as is, clearing the row's old tag
+            return self.update_compressed(key, value, usize::MAX);
+        }
+
+        let mut statement = self.connection.prepare_cached(&format!(
+            "UPDATE {} SET {} = ?1, {} = {} WHERE {} = ?2",
+            self.name, VALUE_COLUMN, UPDATED_AT_COLUMN, NOW, KEY_COLUMN
+        ))?;
+        let result = statement.execute(params![value, key])?;
+
+        Ok(result)
+    }
+
+    /// Get the last modified time of a key
+    ///
+    /// Returns the `updated_at` timestamp of the key as seconds since the
+    /// unix epoch, or `None` if the key is missing from the table. Useful
+    /// for conditional-GET style logic such as `Last-Modified` headers.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// let modified = table.last_modified("jimmy")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn last_modified(&self, key: &str) -> Result<Option<i64>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
+            UPDATED_AT_COLUMN, self.name, KEY_COLUMN
+        ))?;
+        let value = statement
+            .query_row(params![key], |row| row.get::<usize, i64>(0))
+            .optional()?;
+
+        Ok(value)
+    }
+
+    /// Get a value along with how long ago it was last updated
+    ///
+    /// The age is measured from the key's `updated_at` timestamp, which has
+    /// second precision, to the current system time. Returns `None` if the
+    /// key is missing from the table. Useful for cache diagnostics, tables
+    /// without the timestamp columns (format version 1) fail with an error.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// if let Some((value, age)) = table.get_timed::<String>("jimmy")? {
+    ///     println!("{} is {:?} old", value, age);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get_timed<T: FromSql>(&self, key: &str) -> Result<Option<(T, Duration)>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, UPDATED_AT_COLUMN, self.name, KEY_COLUMN
+        ))?;
+        let value = statement
+            .query_row(params![key], |row| {
+                Ok((row.get::<usize, T>(0)?, row.get::<usize, i64>(1)?))
+            })
+            .optional()?;
+
+        Ok(value.map(|(value, updated_at)| {
+            let updated_at = UNIX_EPOCH + Duration::from_secs(updated_at.max(0) as u64);
+            let age = SystemTime::now()
+                .duration_since(updated_at)
+                .unwrap_or(Duration::ZERO);
+
+            (value, age)
+        }))
+    }
+
+    /// Bumps a key's `updated_at` timestamp without changing its value
+    ///
+    /// Useful for sliding expiration, refreshing a key whenever it is
+    /// accessed. Returns `true` if the key exists and was touched.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// assert!(table.touch("jimmy")?);
+    /// assert!(!table.touch("bob")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn touch(&self, key: &str) -> Result<bool, Error> {
+        let touched = self.connection.execute(
+            &format!(
+                "UPDATE {} SET {} = {} WHERE {} = ?1",
+                self.name, UPDATED_AT_COLUMN, NOW, KEY_COLUMN
+            ),
+            params![key],
+        )?;
+
+        Ok(touched > 0)
+    }
+
+    /// Get a value by key, checking its stored type first
+    ///
+    /// SQLite tags every value with a storage class, one of `integer`,
+    /// `real`, `text`, `blob` or `null` (as reported by `typeof`). This
+    /// compares the stored tag against `expected_tag` before casting the
+    /// value to `T`, returning an `Error::InvalidColumnType` on mismatch
+    /// rather than silently converting the data.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", &123)?;
+    ///
+    /// let result = table.get_checked::<i64>("jimmy", "integer")?;
+    /// assert!(table.get_checked::<String>("jimmy", "text").is_err());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get_checked<T: FromSql>(&self, key: &str, expected_tag: &str) -> Result<T, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT typeof({}), {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, value_column, self.name, KEY_COLUMN
+        ))?;
+        let value = statement.query_row(params![key], |row| {
+            let tag = row.get::<usize, String>(0)?;
+
+            if !tag.eq_ignore_ascii_case(expected_tag) {
+                return Err(Error::InvalidColumnType(
+                    1,
+                    VALUE_COLUMN.to_owned(),
+                    row.get_ref(1)?.data_type(),
+                ));
+            }
+
+            row.get::<usize, T>(1)
+        })?;
+
+        Ok(value)
+    }
+
+    /// Removes data by the key, returning the removed value
+    ///
+    /// Uses SQLite's `DELETE ... RETURNING` to remove the key and read its
+    /// value in one statement. Returns `None` if the key was missing from
+    /// the table.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// let removed = table.remove_returning::<String>("jimmy")?;
+    /// assert_eq!(Some("abc".to_owned()), removed);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn remove_returning<T: FromSql>(&self, key: &str) -> Result<Option<T>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "DELETE FROM {} WHERE {} = ?1 RETURNING {}",
+            self.name, KEY_COLUMN, value_column
+        ))?;
+        let value = statement
+            .query_row(params![key], |row| row.get::<usize, T>(0))
+            .optional()?;
+
+        Ok(value)
+    }
+
+    /// Get a page of entries ordered by key, starting after a given key
+    ///
+    /// Keyset pagination which stays efficient on large tables, unlike
+    /// `OFFSET` based pagination. Pass `None` to start at the beginning of
+    /// the table and the last key of the previous page to get the next.
+    /// An empty page means there are no more entries.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let first = table.page_after::<String>(None, 10)?;
+    ///
+    /// if let Some(last) = first.last() {
+    ///     let next = table.page_after::<String>(Some(&last.key), 10)?;
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn page_after<T: FromSql>(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE ?1 IS NULL OR {} > ?1 ORDER BY {} LIMIT ?2",
+            KEY_COLUMN, value_column, self.name, KEY_COLUMN, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![after_key, limit as i64], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Removes data by the key, returning whether it was removed
+    ///
+    /// A more readable alternative to `remove` when the row count isn't
+    /// needed. Returns `true` if exactly one row was removed and `false`
+    /// if the key was missing from the table.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// if table.remove_one("jimmy")? {
+    ///     println!("jimmy was removed");
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn remove_one(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.remove(key)? == 1)
+    }
+
+    /// Removes a key only if its current value matches
+    ///
+    /// The comparison is done by SQLite, so the stored value must have the
+    /// same type as `expected`, e.g. the text `"1"` doesn't match the
+    /// integer `1`. Returns `true` if a row was removed.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("locks").create(&connection)?;
+    /// let table = Table::existing("locks", &connection);
+    ///
+    /// table.set("job", "worker-1")?;
+    ///
+    /// assert!(!table.remove_if("job", "worker-2")?);
+    /// assert!(table.remove_if("job", "worker-1")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, expected), fields(table = self.name)))]
+    pub fn remove_if<T: ToSql + ?Sized>(&self, key: &str, expected: &T) -> Result<bool, Error> {
+        let value_column = self.value_sql()?;
+        let removed = self.connection.execute(
+            &format!(
+                "DELETE FROM {} WHERE {} = ?1 AND {} = ?2",
+                self.name, KEY_COLUMN, value_column
+            ),
+            params![key, expected],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    /// Writes a value only if it differs from the stored one
+    ///
+    /// Reads the current value and compares it in Rust, skipping the write
+    /// when it's equal so `updated_at` and update hooks aren't triggered
+    /// needlessly. A missing key is inserted and a stored value of another
+    /// type counts as different. Runs within one transaction and returns
+    /// `true` if a write occurred.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("config").create(&connection)?;
+    /// let table = Table::existing("config", &connection);
+    ///
+    /// assert!(table.set_if_changed("retries", &3)?);
+    /// assert!(!table.set_if_changed("retries", &3)?);
+    /// assert!(table.set_if_changed("retries", &5)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value), fields(table = self.name)))]
+    pub fn set_if_changed<T: ToSql + FromSql + PartialEq>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> Result<bool, Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        match self.read::<T>(key) {
+            Ok(current) if current == *value => return Ok(false),
+            Ok(_) | Err(Error::InvalidColumnType(..)) => self.update(key, value)?,
+            Err(Error::QueryReturnedNoRows) => self.set(key, value)?,
+            Err(err) => return Err(err),
+        };
+
+        transaction.commit()?;
+
+        Ok(true)
+    }
+
+    /// Transforms a stored value in place
+    ///
+    /// Reads the value of the key, applies `f` and writes the result back
+    /// within one transaction. Returns the new value, or `None` without
+    /// calling `f` if the key is missing from the table.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("counters").create(&connection)?;
+    /// let table = Table::existing("counters", &connection);
+    ///
+    /// table.set("visits", &1)?;
+    ///
+    /// let visits = table.map_value("visits", |visits: i64| visits + 1)?;
+    /// assert_eq!(Some(2), visits);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, f), fields(table = self.name)))]
+    pub fn map_value<T: ToSql + FromSql>(
+        &self,
+        key: &str,
+        f: impl FnOnce(T) -> T,
+    ) -> Result<Option<T>, Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let value = match self.read::<T>(key).optional()? {
+            Some(value) => f(value),
+            None => return Ok(None),
+        };
+
+        self.update(key, &value)?;
+        transaction.commit()?;
+
+        Ok(Some(value))
+    }
+
+    /// Inserts a string value into the table
+    ///
+    /// A non-generic shorthand for `set` with string values.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set_str("jimmy", "abc@abc.com")?;
+    ///
+    /// let email = table.get_str("jimmy")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn set_str(&self, key: &str, value: &str) -> RusqliteResponse {
+        self.set(key, value)
+    }
+
+    /// Get a string value in the database by key
+    ///
+    /// A non-generic shorthand for `get::<String>`.
+    pub fn get_str(&self, key: &str) -> Result<String, Error> {
+        self.get::<String>(key)
+    }
+
+    /// Inserts a floating point value into the table
+    ///
+    /// A non-generic shorthand for `set` with `f64` values. The value
+    /// column has no declared type so SQLite keeps the value as a `REAL`
+    /// and `get_float` reads back exactly the same bits. `f32` values can
+    /// be widened with `f64::from` without any loss.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("prices").create(&connection)?;
+    /// let table = Table::existing("prices", &connection);
+    ///
+    /// table.set_float("beans", 2.75)?;
+    ///
+    /// assert_eq!(2.75, table.get_float("beans")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn set_float(&self, key: &str, value: f64) -> RusqliteResponse {
+        self.set(key, &value)
+    }
+
+    /// Get a floating point value in the database by key
+    ///
+    /// A non-generic shorthand for `get::<f64>`. Integer values are
+    /// converted to `f64`.
+    pub fn get_float(&self, key: &str) -> Result<f64, Error> {
+        self.get::<f64>(key)
+    }
+
+    /// Get every entry updated after a given time
+    ///
+    /// Returns the entries whose `updated_at` timestamp is greater than
+    /// `since` (seconds since the unix epoch), oldest changes first. This
+    /// supports incremental syncing to another store by remembering the
+    /// time of the last sync.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let changes = table.changed_since::<String>(1_700_000_000)?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn changed_since<T: FromSql>(&self, since: i64) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} > ?1 ORDER BY {}, {}",
+            KEY_COLUMN, value_column, self.name, UPDATED_AT_COLUMN, UPDATED_AT_COLUMN, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![since], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get a value by key, computing a default when the key is missing
+    ///
+    /// `f` is only called if the key is missing from the table, avoiding
+    /// building expensive defaults needlessly. Other errors, such as a
+    /// failed cast, are still returned.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let email = table.get_or_else("jimmy", || "unknown".to_owned())?;
+    /// assert_eq!("unknown", email);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn get_or_else<T: FromSql>(&self, key: &str, f: impl FnOnce() -> T) -> Result<T, Error> {
+        Ok(self.get::<T>(key).optional()?.unwrap_or_else(f))
+    }
+
+    /// Get several values at once, keeping each key's outcome separate
+    ///
+    /// Every key is read independently so one value failing to cast
+    /// doesn't fail the whole batch. Each key is paired with `Ok(Some(_))`
+    /// when found, `Ok(None)` when missing or the error for that key, in
+    /// the same order as `keys`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("counters").create(&connection)?;
+    /// let table = Table::existing("counters", &connection);
+    ///
+    /// table.set("visits", &3)?;
+    /// table.set("name", "jimmy")?;
+    ///
+    /// for (key, result) in table.get_results::<i64>(&["visits", "name", "missing"]) {
+    ///     match result {
+    ///         Ok(Some(count)) => println!("{} = {}", key, count),
+    ///         Ok(None) => println!("{} is missing", key),
+    ///         Err(err) => println!("{} failed: {}", key, err),
+    ///     }
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn get_results<T: FromSql>(
+        &self,
+        keys: &[&str],
+    ) -> Vec<(String, Result<Option<T>, Error>)> {
+        keys.iter()
+            .map(|key| (key.to_string(), self.get::<T>(key).optional()))
+            .collect()
+    }
+
+    /// Get the values of keys read from `reader`, one key per line
+    ///
+    /// Intended for batch jobs working from a file of keys. Blank lines
+    /// are skipped and trailing `\r` line endings removed. Each key is
+    /// paired with its value or `None` when missing, in the order read.
+    /// Failing to read a line is returned as
+    /// `Error::ToSqlConversionFailure`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// let values = table.get_from_reader::<_, String>("jimmy\nbob\n".as_bytes())?;
+    /// assert_eq!(
+    ///     vec![("jimmy".to_owned(), Some("abc".to_owned())), ("bob".to_owned(), None)],
+    ///     values
+    /// );
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn get_from_reader<R: Read, T: FromSql>(
+        &self,
+        reader: R,
+    ) -> Result<Vec<(String, Option<T>)>, Error> {
+        let mut values = Vec::new();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line.map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+            let key = line.trim_end_matches('\r');
+
+            if key.is_empty() {
+                continue;
+            }
+
+            let value = self.get::<T>(key).optional()?;
+            values.push((key.to_owned(), value));
+        }
+
+        Ok(values)
+    }
+
+    /// Get a value by key, returning `T::default()` when the key is missing
+    ///
+    /// Handy for counters and flags which start at `0` or `false`. Other
+    /// errors, such as a failed cast, are still returned.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("counters").create(&connection)?;
+    /// let table = Table::existing("counters", &connection);
+    ///
+    /// assert_eq!(0, table.get_or_default::<i64>("visits")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn get_or_default<T: FromSql + Default>(&self, key: &str) -> Result<T, Error> {
+        self.get_or_else(key, T::default)
+    }
+
+    /// Get every key in the table
+    ///
+    /// Keys are returned in ascending order.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// assert_eq!(vec!["jimmy".to_owned()], table.keys()?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn keys(&self) -> Result<Vec<String>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} ORDER BY {}",
+            KEY_COLUMN, self.name, KEY_COLUMN
+        ))?;
+        let keys = statement
+            .query_map([], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, Error>>()?;
+
+        Ok(keys)
+    }
+
+    /// Count the rows in the table
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// assert_eq!(1, table.count()?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn count(&self) -> Result<usize, Error> {
+        self.connection
+            .query_row(&format!("SELECT COUNT(*) FROM {}", self.name), [], |row| {
+                row.get(0)
+            })
+    }
+
+    /// Lazily iterate over every key in the table
+    ///
+    /// A memory efficient alternative to `keys` for large tables. Rather
+    /// than holding a statement open for the lifetime of the iterator, keys
+    /// are fetched in ascending order a chunk at a time, continuing after
+    /// the last row seen, so every copy of a key on an
+    /// `allow_duplicate_keys` table is yielded like `keys` does. Keys
+    /// inserted or removed while iterating may or may not be yielded
+    /// depending on where they sort.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// for key in table.keys_iter()? {
+    ///     println!("{}", key?);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn keys_iter(&self) -> Result<impl Iterator<Item = Result<String, Error>> + '_, Error> {
+        let tiebreak = self.tiebreak_column()?;
+        let sql = format!(
+            "SELECT {k}, {t} FROM {} WHERE ?1 IS NULL OR {k} > ?1 OR ({k} = ?1 AND {t} > ?2)
+            ORDER BY {k}, {t} LIMIT ?3",
+            self.name,
+            k = KEY_COLUMN,
+            t = tiebreak
+        );
+
+        // Prepare up front so a missing table errors here rather than mid iteration
+        self.connection.prepare_cached(&sql)?;
+
+        let mut after: Option<(String, Value)> = None;
+        let mut buffer = VecDeque::new();
+        let mut done = false;
+
+        Ok(std::iter::from_fn(move || {
+            if buffer.is_empty() && !done {
+                let (after_key, after_tiebreak) = match &after {
+                    Some((key, tiebreak)) => (Some(key), tiebreak.clone()),
+                    None => (None, Value::Null),
+                };
+                let chunk = self
+                    .connection
+                    .prepare_cached(&sql)
+                    .and_then(|mut statement| {
+                        statement
+                            .query_map(
+                                params![after_key, after_tiebreak, KEYS_ITER_CHUNK_SIZE as i64],
+                                |row| {
+                                    Ok((row.get::<usize, String>(0)?, row.get::<usize, Value>(1)?))
+                                },
+                            )?
+                            .collect::<Result<Vec<(String, Value)>, Error>>()
+                    });
+
+                match chunk {
+                    Ok(rows) => {
+                        done = rows.len() < KEYS_ITER_CHUNK_SIZE;
+                        after = rows.last().cloned();
+                        buffer.extend(rows.into_iter().map(|(key, _)| key));
+                    }
+                    Err(err) => {
+                        done = true;
+                        return Some(Err(err));
+                    }
+                }
+            }
+
+            buffer.pop_front().map(Ok)
+        }))
+    }
+
+    /// The column ordering rows that share a key, for paging through them
+    ///
+    /// The rowid, or the key itself for `WITHOUT ROWID` tables, whose keys
+    /// are always unique. A missing table is treated as a rowid table.
+    fn tiebreak_column(&self) -> Result<&'static str, Error> {
+        let without_rowid = self
+            .connection
+            .query_row(
+                "SELECT wr FROM pragma_table_list WHERE schema = 'main' AND name = ?1",
+                params![self.name],
+                |row| row.get::<usize, bool>(0),
+            )
+            .optional()?
+            .unwrap_or(false);
+
+        Ok(if without_rowid { KEY_COLUMN } else { "rowid" })
+    }
+
+    /// Get every value stored under a key
+    ///
+    /// Intended for tables created with `allow_duplicate_keys`, values are
+    /// returned in the order they were inserted. A missing key returns an
+    /// empty `Vec`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("events").allow_duplicate_keys().create(&connection)?;
+    /// let events = Table::existing("events", &connection);
+    ///
+    /// let history = events.get_all_for_key::<String>("jimmy")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get_all_for_key<T: FromSql>(&self, key: &str) -> Result<Vec<T>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} WHERE {} = ?1 ORDER BY rowid",
+            value_column, self.name, KEY_COLUMN
+        ))?;
+        let values = statement
+            .query_map(params![key], |row| row.get::<usize, T>(0))?
+            .collect::<Result<Vec<T>, Error>>()?;
+
+        Ok(values)
+    }
+
+    /// Appends an event to the log stored under a key
+    ///
+    /// Models an append-only event log keyed by aggregate id on a table
+    /// created with `allow_duplicate_keys`, each call inserts a new row and
+    /// returns its rowid. On a table with unique keys only the first append
+    /// per key succeeds. Read the events back with `read_log`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("events").allow_duplicate_keys().create(&connection)?;
+    /// let events = Table::existing("events", &connection);
+    ///
+    /// let first = events.append("order-1", "created")?;
+    /// let second = events.append("order-1", "paid")?;
+    ///
+    /// assert!(second > first);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value), fields(table = self.name)))]
+    pub fn append<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<i64, Error> {
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            self.set_compressed(key, value, threshold)?;
+            return Ok(self.connection.last_insert_rowid());
+        }
+
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} ({}, {}) VALUES(?1, ?2)",
+                self.name, KEY_COLUMN, VALUE_COLUMN
+            ),
+            params![key, value],
+        )?;
+
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Reads every event appended under a key, oldest first
+    ///
+    /// The counterpart to `append`, events are returned in rowid order.
+    /// Same as `get_all_for_key`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("events").allow_duplicate_keys().create(&connection)?;
+    /// let events = Table::existing("events", &connection);
+    ///
+    /// events.append("order-1", "created")?;
+    /// events.append("order-1", "paid")?;
+    ///
+    /// assert_eq!(vec!["created", "paid"], events.read_log::<String>("order-1")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn read_log<T: FromSql>(&self, key: &str) -> Result<Vec<T>, Error> {
+        self.get_all_for_key(key)
+    }
+
+    /// Sums the numeric values in the table
+    ///
+    /// Only values stored as integers or reals are included, text such as
+    /// `"123"` and blobs are ignored rather than converted with SQLite's
+    /// loose numeric rules. Uses SQLite's `total` so an empty table sums
+    /// to `0.0`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("scores").create(&connection)?;
+    /// let table = Table::existing("scores", &connection);
+    ///
+    /// table.set("jimmy", &10)?;
+    /// table.set("bob", &20)?;
+    ///
+    /// assert_eq!(30.0, table.sum()?);
+    /// assert_eq!(Some(15.0), table.avg()?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn sum(&self) -> Result<f64, Error> {
+        Ok(self.aggregate("total")?.unwrap_or(0.0))
+    }
+
+    /// Averages the numeric values in the table
+    ///
+    /// Follows the same rules as `sum`, returns `None` when there are no
+    /// numeric values.
+    pub fn avg(&self) -> Result<Option<f64>, Error> {
+        self.aggregate("avg")
+    }
+
+    /// Get the smallest numeric value in the table
+    ///
+    /// Follows the same rules as `sum`, returns `None` when there are no
+    /// numeric values.
+    pub fn min(&self) -> Result<Option<f64>, Error> {
+        self.aggregate("min")
+    }
+
+    /// Get the largest numeric value in the table
+    ///
+    /// Follows the same rules as `sum`, returns `None` when there are no
+    /// numeric values.
+    pub fn max(&self) -> Result<Option<f64>, Error> {
+        self.aggregate("max")
+    }
+
+    /// Runs an SQLite aggregate function over the numeric values
+    fn aggregate(&self, function: &str) -> Result<Option<f64>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}({}) FROM {} WHERE typeof({}) IN ('integer', 'real')",
+            function, VALUE_COLUMN, self.name, VALUE_COLUMN
+        ))?;
+        let value = statement.query_row([], |row| row.get::<usize, Option<f64>>(0))?;
+
+        Ok(value)
+    }
+
+    /// Get a value by key as the first of two types it can be read as
+    ///
+    /// Tries to read the value as `T` and falls back to `U` if that fails,
+    /// useful for legacy data where the same key may have been stored as
+    /// different types. Errors if the key is missing or neither type fits.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Either, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "42")?;
+    ///
+    /// match table.get_as::<i64, String>("jimmy")? {
+    ///     Either::Left(number) => println!("number {}", number),
+    ///     Either::Right(text) => println!("text {}", text),
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get_as<T: FromSql, U: FromSql>(&self, key: &str) -> Result<Either<T, U>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, self.name, KEY_COLUMN
+        ))?;
+        let value = statement.query_row(params![key], |row| {
+            row.get::<usize, T>(0)
+                .map(Either::Left)
+                .or_else(|_| row.get::<usize, U>(0).map(Either::Right))
+        })?;
+
+        Ok(value)
+    }
+
+    /// Renames the table
+    ///
+    /// Runs `ALTER TABLE ... RENAME TO` after validating the new name with
+    /// `sanitize_identifier`. Data is kept, as is a `full_text_search`
+    /// index which is renamed along with the table. The returned
+    /// `DatabaseTable` refers to the renamed table and this one should no
+    /// longer be used.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let customers = table.rename_table("customers")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn rename_table(&self, new_name: &'a str) -> Result<DatabaseTable<'a>, Error> {
+        sanitize_identifier(new_name)?;
+
+        let transaction = Scope::begin(self.connection)?;
+        self.connection.execute(
+            &format!("ALTER TABLE {} RENAME TO {}", self.name, new_name),
+            (),
+        )?;
+        #[cfg(feature = "fts")]
+        crate::fts::rename_index(self.name, new_name, self.connection)?;
+        transaction.commit()?;
+
+        Ok(self.with_name(new_name))
+    }
+
+    /// Creates an empty table with the same schema as this one
+    ///
+    /// Reads this table's `CREATE TABLE` statement (see `describe`), swaps
+    /// in `new_name` after validating it with `sanitize_identifier` and runs
+    /// it. Extra columns added to the source table are copied too, indexes
+    /// and triggers aren't. Fails if a table named `new_name` exists.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let shard = table.copy_schema("users_2")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn copy_schema(&self, new_name: &'a str) -> Result<DatabaseTable<'a>, Error> {
+        sanitize_identifier(new_name)?;
+
+        let sql = self.describe()?;
+        let columns = sql.find('(').ok_or_else(|| table_not_found(self.name))?;
+
+        self.connection.execute(
+            &format!("CREATE TABLE {} {}", new_name, &sql[columns..]),
+            (),
+        )?;
+
+        Ok(self.with_name(new_name))
+    }
+
+    /// Removes every key starting with a prefix
+    ///
+    /// Evicts a whole namespace of keys, e.g. `cache:user:`, in a single
+    /// atomic statement and returns how many were removed. The prefix is
+    /// matched exactly and case sensitively.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("cache").create(&connection)?;
+    /// let table = Table::existing("cache", &connection);
+    ///
+    /// table.set("user:1", "jimmy")?;
+    /// table.set("user:2", "bob")?;
+    ///
+    /// assert_eq!(2, table.sweep_prefix("user:")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn sweep_prefix(&self, prefix: &str) -> RusqliteResponse {
+        let result = self.connection.execute(
+            &format!("DELETE FROM {} WHERE {}", self.name, prefix_condition()),
+            params![prefix],
+        )?;
+
+        Ok(result)
+    }
+
+    /// Lazily iterate over every entry whose key starts with a prefix
+    ///
+    /// A memory efficient alternative to `scan_prefix_stripped` for large
+    /// namespaces. Like `keys_iter`, entries are fetched in ascending key
+    /// order a chunk at a time rather than holding a statement open, and
+    /// every copy of a duplicate key is yielded. The prefix is matched
+    /// exactly and case sensitively.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("cache").create(&connection)?;
+    /// let table = Table::existing("cache", &connection);
+    ///
+    /// table.set("user:1", "jimmy")?;
+    ///
+    /// for entry in table.iter_prefix::<String>("user:")? {
+    ///     let entry = entry?;
+    ///     println!("{} = {}", entry.key, entry.value);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn iter_prefix<'s, T: FromSql + 's>(
+        &'s self,
+        prefix: &str,
+    ) -> Result<impl Iterator<Item = Result<KeyValue<T>, Error>> + 's, Error> {
+        let value_column = self.value_sql()?;
+        let tiebreak = self.tiebreak_column()?;
+        let sql = format!(
+            "SELECT {k}, {}, {t} FROM {} WHERE {}
+            AND (?2 IS NULL OR {k} > ?2 OR ({k} = ?2 AND {t} > ?3))
+            ORDER BY {k}, {t} LIMIT ?4",
+            value_column,
+            self.name,
+            prefix_condition(),
+            k = KEY_COLUMN,
+            t = tiebreak
+        );
+
+        // Prepare up front so a missing table errors here rather than mid iteration
+        self.connection.prepare_cached(&sql)?;
+
+        let prefix = prefix.to_owned();
+        let mut after: Option<(String, Value)> = None;
+        let mut buffer = VecDeque::new();
+        let mut done = false;
+
+        Ok(std::iter::from_fn(move || {
+            if buffer.is_empty() && !done {
+                let (after_key, after_tiebreak) = match &after {
+                    Some((key, tiebreak)) => (Some(key), tiebreak.clone()),
+                    None => (None, Value::Null),
+                };
+                let chunk = self
+                    .connection
+                    .prepare_cached(&sql)
+                    .and_then(|mut statement| {
+                        statement
+                            .query_map(
+                                params![
+                                    prefix,
+                                    after_key,
+                                    after_tiebreak,
+                                    KEYS_ITER_CHUNK_SIZE as i64
+                                ],
+                                |row| Ok((KeyValue::from_row(row)?, row.get::<usize, Value>(2)?)),
+                            )?
+                            .collect::<Result<Vec<(KeyValue<T>, Value)>, Error>>()
+                    });
+
+                match chunk {
+                    Ok(rows) => {
+                        done = rows.len() < KEYS_ITER_CHUNK_SIZE;
+                        after = rows
+                            .last()
+                            .map(|(entry, tiebreak)| (entry.key.clone(), tiebreak.clone()));
+                        buffer.extend(rows.into_iter().map(|(entry, _)| entry));
+                    }
+                    Err(err) => {
+                        done = true;
+                        return Some(Err(err));
+                    }
+                }
+            }
+
+            buffer.pop_front().map(Ok)
+        }))
+    }
+
+    /// Removes every key matching a predicate
+    ///
+    /// Only keys are read, values are never fetched. Keys are evaluated
+    /// and the matching rows deleted within one transaction, returning how
+    /// many rows were removed.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    /// table.set("bob", "def")?;
+    ///
+    /// assert_eq!(1, table.remove_where_key(|key| key.starts_with('j'))?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn remove_where_key(&self, f: impl Fn(&str) -> bool) -> RusqliteResponse {
+        let transaction = Scope::begin(self.connection)?;
+
+        let keys = self.keys()?;
+        let mut statement = transaction.prepare(&format!(
+            "DELETE FROM {} WHERE {} = ?1",
+            self.name, KEY_COLUMN
+        ))?;
+        let mut removed = 0;
+
+        for key in keys.iter().filter(|key| f(key)) {
+            removed += statement.execute(params![key])?;
+        }
+
+        drop(statement);
+        transaction.commit()?;
+
+        Ok(removed)
+    }
+
+    /// Get every entry whose key starts with a prefix, with the prefix removed
+    ///
+    /// Handy when scanning a namespace such as `user:` where only the rest
+    /// of the key is interesting. Entries are ordered by key and the prefix
+    /// is matched exactly and case sensitively.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("cache").create(&connection)?;
+    /// let table = Table::existing("cache", &connection);
+    ///
+    /// table.set("user:1", "jimmy")?;
+    ///
+    /// let users = table.scan_prefix_stripped::<String>("user:")?;
+    /// assert_eq!("1", users[0].key);
+    /// assert_eq!("jimmy", users[0].value);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn scan_prefix_stripped<T: FromSql>(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} ORDER BY {}",
+            KEY_COLUMN,
+            value_column,
+            self.name,
+            prefix_condition(),
+            KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![prefix], KeyValue::from_row)?
+            .map(|entry| {
+                entry.map(|mut entry| {
+                    entry.key = entry.key.split_off(prefix.len());
+                    entry
+                })
+            })
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get the first `limit` entries whose key starts with a prefix
+    ///
+    /// Entries are ordered by key and the prefix is matched exactly and
+    /// case sensitively, like `scan_prefix_stripped` but keeping the full
+    /// key. Only the requested rows are read, so previewing a large
+    /// namespace doesn't pull all of it.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("posts").create(&connection)?;
+    /// let table = Table::existing("posts", &connection);
+    ///
+    /// table.set_many(&[("post:1", "a"), ("post:2", "b"), ("post:3", "c")])?;
+    ///
+    /// let preview = table.scan_prefix_limit::<String>("post:", 2)?;
+    /// assert_eq!(2, preview.len());
+    /// assert_eq!("post:1", preview[0].key);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn scan_prefix_limit<T: FromSql>(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} ORDER BY {} LIMIT ?2",
+            KEY_COLUMN,
+            value_column,
+            self.name,
+            prefix_condition(),
+            KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![prefix, limit], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get every entry whose value contains a substring
+    ///
+    /// A naive substring search using `LIKE`, not full-text search, so it
+    /// scans every row and can't use an index. `%` & `_` in `needle` are
+    /// matched literally, and as with `LIKE` ASCII letters match case
+    /// insensitively. Non-text values are matched against their text form.
+    /// Entries are ordered by key.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("posts").create(&connection)?;
+    /// let table = Table::existing("posts", &connection);
+    ///
+    /// table.set("first", "Hello world")?;
+    /// table.set("second", "Goodbye")?;
+    ///
+    /// let found = table.search_values::<String>("world")?;
+    /// assert_eq!(1, found.len());
+    /// assert_eq!("first", found[0].key);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn search_values<T: FromSql>(&self, needle: &str) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} LIKE '%' || ?1 || '%' ESCAPE '\\' ORDER BY {}",
+            KEY_COLUMN, value_column, self.name, value_column, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![escape_like(needle)], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get every entry matching a custom SQL condition
+    ///
+    /// `condition` is placed in the `WHERE` clause as is, it can reference
+    /// the `k`, `v`, `created_at` & `updated_at` columns and any SQL or
+    /// registered function. Entries are ordered by key.
+    ///
+    /// **DANGER:** the condition is raw SQL, never build it from user input,
+    /// pass values through `params` instead.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::{params, Connection};
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("scores").create(&connection)?;
+    /// let table = Table::existing("scores", &connection);
+    ///
+    /// table.set("jimmy", &10)?;
+    /// table.set("bob", &3)?;
+    ///
+    /// let high = table.query::<i64, _>("v > ?1", params![5])?;
+    /// assert_eq!("jimmy", high[0].key);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, params), fields(table = self.name)))]
+    pub fn query<T: FromSql, P: Params>(
+        &self,
+        condition: &str,
+        params: P,
+    ) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} ORDER BY {}",
+            KEY_COLUMN, value_column, self.name, condition, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params, KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get every entry with a key in `[start, end)`
+    ///
+    /// Keys are compared as text, so sortable keys such as zero padded
+    /// timestamps (`2024-01-01T00:00:00`) work well for time series.
+    /// Entries are ordered by key, `start` is included and `end` isn't.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("events").create(&connection)?;
+    /// let table = Table::existing("events", &connection);
+    ///
+    /// table.set("2024-01-15", "signed in")?;
+    /// table.set("2024-02-01", "signed out")?;
+    ///
+    /// let january = table.range::<String>("2024-01", "2024-02")?;
+    /// assert_eq!(1, january.len());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn range<T: FromSql>(&self, start: &str, end: &str) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} >= ?1 AND {} < ?2 ORDER BY {}",
+            KEY_COLUMN, value_column, self.name, KEY_COLUMN, KEY_COLUMN, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![start, end], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get up to `n` random entries
+    ///
+    /// Useful for eviction heuristics and testing. Uses
+    /// `ORDER BY RANDOM() LIMIT n`, which sorts every row of the table and
+    /// is therefore O(n log n) in the table size, so it's unsuitable for
+    /// very large tables. Returns every entry, shuffled, when the table has
+    /// fewer than `n` rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    /// table.set("bob", "def")?;
+    ///
+    /// let sample = table.sample::<String>(1)?;
+    /// assert_eq!(1, sample.len());
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn sample<T: FromSql>(&self, n: usize) -> Result<Vec<KeyValue<T>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} ORDER BY RANDOM() LIMIT ?1",
+            KEY_COLUMN, value_column, self.name
+        ))?;
+        let entries = statement
+            .query_map(params![n as i64], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<T>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Copies the table into a separate SQLite file
+    ///
+    /// The file is created if needed and any table of the same name in it
+    /// is replaced, leaving other tables in the file untouched. The table
+    /// is recreated with its exact schema before the rows are copied over
+    /// using `ATTACH` & `INSERT ... SELECT`, which also works for in-memory
+    /// databases.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open("./data.sqlite")?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.backup_to_file("./users-backup.sqlite")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn backup_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let schema = self.describe()?;
+
+        let mut backup = Connection::open(&path)?;
+        let transaction = backup.transaction()?;
+        transaction.execute(&format!("DROP TABLE IF EXISTS {}", self.name), ())?;
+        transaction.execute(&schema, ())?;
+        transaction.commit()?;
+        drop(backup);
+
+        let path = path.as_ref().to_string_lossy();
+        self.connection
+            .execute("ATTACH DATABASE ?1 AS adrodb_backup", params![path])?;
+
+        let copied = self.connection.execute(
+            &format!(
+                "INSERT INTO adrodb_backup.{} SELECT * FROM main.{}",
+                self.name, self.name
+            ),
+            (),
+        );
+        let detached = self.connection.execute("DETACH DATABASE adrodb_backup", ());
+
+        copied?;
+        detached?;
+
+        Ok(())
+    }
+
+    /// Returns the `CREATE TABLE` statement SQLite stored for the table
+    ///
+    /// Reads the exact SQL from `sqlite_master`, which helps debug schema
+    /// drift or confirm builder options landed. Errors if the table is
+    /// missing.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// println!("{}", table.describe()?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn describe(&self) -> Result<String, Error> {
+        self.connection.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![self.name],
+            |row| row.get::<usize, String>(0),
+        )
+    }
+
+    /// Runs `op`, retrying when SQLite reports the database is busy
+    ///
+    /// Only `SQLITE_BUSY` and `SQLITE_LOCKED` errors are retried, which are
+    /// transient under concurrent writers, any other error is returned
+    /// straight away. `op` runs at most `attempts` times (at least once),
+    /// sleeping for `backoff` before the first retry and doubling the wait
+    /// each time.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use std::time::Duration;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.with_retry(3, Duration::from_millis(10), || table.set("jimmy", "abc"))?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn with_retry<R>(
+        &self,
+        attempts: usize,
+        backoff: Duration,
+        op: impl Fn() -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut wait = backoff;
+
+        for _ in 1..attempts {
+            match op() {
+                Err(Error::SqliteFailure(error, _))
+                    if matches!(
+                        error.code,
+                        ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked
+                    ) =>
+                {
+                    std::thread::sleep(wait);
+                    wait *= 2;
+                }
+                result => return result,
+            }
+        }
+
+        op()
+    }
+
+    /// Get a value and its rowid by key
+    ///
+    /// Returns the SQLite rowid of the entry together with its value, or
+    /// `None` if the key is missing from the table. Pairs with
+    /// `key_for_rowid` for rowid based workflows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// if let Some((rowid, value)) = table.get_with_rowid::<String>("jimmy")? {
+    ///     println!("{} is row {}", value, rowid);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn get_with_rowid<T: FromSql>(&self, key: &str) -> Result<Option<(i64, T)>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT rowid, {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, self.name, KEY_COLUMN
+        ))?;
+        let value = statement
+            .query_row(params![key], |row| {
+                Ok((row.get::<usize, i64>(0)?, row.get::<usize, T>(1)?))
+            })
+            .optional()?;
+
+        Ok(value)
+    }
+
+    /// Looks up the key of a row by its rowid
+    ///
+    /// Returns `None` if no row has the rowid, e.g. after it was deleted.
+    pub fn key_for_rowid(&self, rowid: i64) -> Result<Option<String>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} WHERE rowid = ?1",
+            KEY_COLUMN, self.name
+        ))?;
+        let key = statement
+            .query_row(params![rowid], |row| row.get::<usize, String>(0))
+            .optional()?;
+
+        Ok(key)
+    }
+
+    /// Inserts many keys & values in one transaction
+    ///
+    /// Either every entry is inserted or, if any fails (e.g. an existing
+    /// key), none are. Entries are inserted `BATCH_CHUNK_SIZE` at a time
+    /// with multi-row `INSERT`s. Returns the number of inserted rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set_many(&[("jimmy", "abc"), ("bob", "def")])?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn set_many<T: ToSql>(&self, items: &[(&str, T)]) -> RusqliteResponse {
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            return self
+                .set_iter_compressed(items.iter().map(|(key, value)| (key, value)), threshold);
+        }
+
+        let transaction = Scope::begin(self.connection)?;
+        let mut inserted = 0;
+
+        for chunk in items.chunks(BATCH_CHUNK_SIZE) {
+            let rows = vec!["(?, ?)"; chunk.len()].join(", ");
+            let params = chunk
+                .iter()
+                .flat_map(|(key, value)| [key as &dyn ToSql, value as &dyn ToSql]);
+
+            inserted += self.connection.execute(
+                &format!(
+                    "INSERT INTO {} ({}, {}) VALUES {}",
+                    self.name, KEY_COLUMN, VALUE_COLUMN, rows
+                ),
+                params_from_iter(params),
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(inserted)
+    }
+
+    /// Get the values of many keys in one transaction
+    ///
+    /// Keys are looked up `BATCH_CHUNK_SIZE` at a time with `IN (...)`
+    /// queries, all reading the same snapshot of the table. Entries are
+    /// returned in the order of `keys`, keys missing from the table are
+    /// left out and a key given twice is only returned once.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set_many(&[("jimmy", "abc"), ("bob", "def")])?;
+    ///
+    /// let users = table.get_many::<String>(&["jimmy", "sally"])?;
+    /// assert_eq!(1, users.len());
+    /// assert_eq!("abc", users[0].value);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn get_many<T: FromSql>(&self, keys: &[&str]) -> Result<Vec<KeyValue<T>>, Error> {
+        let transaction = Scope::begin(self.connection)?;
+        let value_column = self.value_sql()?;
+        let mut found = HashMap::with_capacity(keys.len());
+
+        for chunk in keys.chunks(BATCH_CHUNK_SIZE) {
+            let mut statement = self.connection.prepare(&format!(
+                "SELECT {}, {} FROM {} WHERE {} IN ({})",
+                KEY_COLUMN,
+                value_column,
+                self.name,
+                KEY_COLUMN,
+                vec!["?"; chunk.len()].join(", ")
+            ))?;
+            let rows = statement.query_map(params_from_iter(chunk), |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, T>(1)?))
+            })?;
+
+            for row in rows {
+                let (key, value) = row?;
+                found.insert(key, value);
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(keys
+            .iter()
+            .filter_map(|key| found.remove_entry(*key))
+            .map(KeyValue::from)
+            .collect())
+    }
+
+    /// Removes many keys in one transaction
+    ///
+    /// Keys are deleted `BATCH_CHUNK_SIZE` at a time with `IN (...)`
+    /// statements, either every chunk is removed or none are. Keys missing
+    /// from the table are ignored. Returns the number of removed rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set_many(&[("jimmy", "abc"), ("bob", "def")])?;
+    ///
+    /// assert_eq!(2, table.remove_many(&["jimmy", "bob", "sally"])?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn remove_many(&self, keys: &[&str]) -> RusqliteResponse {
+        let transaction = Scope::begin(self.connection)?;
+        let mut removed = 0;
+
+        for chunk in keys.chunks(BATCH_CHUNK_SIZE) {
+            removed += self.connection.execute(
+                &format!(
+                    "DELETE FROM {} WHERE {} IN ({})",
+                    self.name,
+                    KEY_COLUMN,
+                    vec!["?"; chunk.len()].join(", ")
+                ),
+                params_from_iter(chunk),
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(removed)
+    }
+
+    /// Inserts keys & values from an iterator in one transaction
+    ///
+    /// Streams the entries into the table without collecting them first,
+    /// useful for bulk loading large data sets. Like `set_many` either
+    /// every entry is inserted or none are. Returns the number of inserted
+    /// rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("squares").create(&connection)?;
+    /// let table = Table::existing("squares", &connection);
+    ///
+    /// table.set_iter((0..100).map(|i| (i.to_string(), i * i)))?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn set_iter<K, T, I>(&self, items: I) -> RusqliteResponse
+    where
+        K: AsRef<str>,
+        T: ToSql,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            return self.set_iter_compressed(items, threshold);
+        }
+
+        let transaction = Scope::begin(self.connection)?;
+        let mut statement = self.connection.prepare(&format!(
+            "INSERT INTO {} ({}, {}) VALUES(?1, ?2);",
+            self.name, KEY_COLUMN, VALUE_COLUMN
+        ))?;
+        let mut inserted = 0;
+
+        for (key, value) in items {
+            inserted += statement.execute(params![key.as_ref(), value])?;
+        }
+
+        drop(statement);
+        transaction.commit()?;
+
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_table_new() {
+        let table = Table::new("test");
+        assert_eq!(table.name, "test");
+    }
+
+    #[test]
+    fn test_table_create() -> Result<(), Error> {
+        let connection = Connection::open_in_memory()?;
+        let table = Table::new("test");
+
+        // Create the table
+        let result = table.create(&connection);
+
+        assert!(result.is_ok());
+
+        // To test that, we attempt to drop the same table.
+        // If it wasn't created, this will return a Err
+        let result = connection.execute("DROP TABLE test", ());
+
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_insert() -> Result<(), Error> {
+        let conn = Connection::open_in_memory()?;
+        let table = Table::new("test");
+        let table = table.create(&conn)?;
+
+        let result = table.set("jimmy", "abc@abc.com");
+        assert!(result.is_ok());
+
+        let mut stmt = conn.prepare(&format!("SELECT * from {}", table.name))?;
+        let mut rows = stmt.query([])?;
+        let first = rows.next()?;
+
+        assert!(first.is_some());
+        let key: String = first.unwrap().get(0)?;
+        assert_eq!("jimmy".to_owned(), key);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::existing("nope", &conn);
+        let result = table.set("key", "value");
+
+        assert!(result.is_err());
+
+        let table = Table::new("users");
+        table.create(&conn).unwrap();
+        let table = Table::existing("users", &conn).set("key1", "value");
+
+        assert!(table.is_ok());
+    }
+
+    #[test]
+    fn test_getting_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = table.create(&conn).unwrap();
+
+        let result = table.get::<String>("jimmy");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_getting_valid_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = table.create(&conn).unwrap();
+
+        table.set("jimmy", "abc").unwrap();
+
+        let result = table.get::<String>("jimmy");
+
+        assert!(result.is_ok());
+        assert_eq!("abc", result.unwrap());
+    }
+
+    #[test]
+    fn test_casting_results() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = table.create(&conn).unwrap();
+
+        table.set("jim", "123").unwrap();
+        table.set("jimmy", &123).unwrap();
+        table.set("bob", &true).unwrap();
+
+        let result = table.get::<i32>("jimmy");
+        assert_eq!(123, result.unwrap());
+
+        let result = table.get::<String>("jim");
+        assert_eq!("123", result.unwrap());
+
+        let result = table.get::<bool>("bob");
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_removing_by_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "bean").unwrap();
+
+        let jimmy = db.get::<String>("jimmy").unwrap();
+        assert_eq!(jimmy, "bean");
+
+        db.remove("jimmy").unwrap();
+
+        let jimmy = db.get::<String>("jimmy");
+        assert!(jimmy.is_err());
+    }
+
+    #[test]
+    fn test_removing_by_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.remove("unknown");
+
+        assert!(result.is_ok());
+        assert_eq!(0, result.unwrap());
+    }
+
+    #[test]
+    fn test_updating_existing_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "123").unwrap();
+
+        let result = db.update("jimmy", "456");
+
+        assert!(result.is_ok());
+        assert_eq!(1, result.unwrap());
+
+        let jimmy = db.get::<String>("jimmy").unwrap();
+
+        assert_eq!(jimmy, "456");
+    }
+
+    #[test]
+    fn test_updating_missing_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.update("jimmy", "456");
+
+        assert!(result.is_ok());
+        assert_eq!(0, result.unwrap());
+
+        let jimmy = db.get::<String>("jimmy");
+
+        assert!(jimmy.is_err());
+    }
+
+    #[test]
+    fn test_last_modified() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        db.set("jimmy", "123").unwrap();
+
+        let modified = db.last_modified("jimmy").unwrap().unwrap();
+        assert!((modified - now).abs() <= 5);
+
+        let missing = db.last_modified("unknown").unwrap();
+        assert_eq!(None, missing);
+    }
+
+    #[test]
+    fn test_get_timed() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        let (value, age) = db.get_timed::<String>("jimmy").unwrap().unwrap();
+
+        assert_eq!("abc", value);
+        assert!(age > Duration::ZERO);
+        assert!(age < Duration::from_secs(5));
+        assert!(db.get_timed::<String>("unknown").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_integrity_check() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "123").unwrap();
+
+        let result = Table::integrity_check(&conn);
+
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_vacuum_into() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vacuum_into.sqlite");
+
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        let posts = Table::new("posts");
+        let posts = posts.create(&conn).unwrap();
+        users.set("jimmy", "abc").unwrap();
+        posts.set("first", "hello").unwrap();
+
+        Table::vacuum_into(&conn, &path).unwrap();
+
+        // The file already exists so it can't be vacuumed into again
+        assert!(Table::vacuum_into(&conn, &path).is_err());
+        assert_eq!("abc", users.get::<String>("jimmy").unwrap());
+
+        let copy = Connection::open(&path).unwrap();
+        assert!(Table::integrity_check(&copy).unwrap());
+        assert_eq!(
+            "abc",
+            Table::existing("users", &copy)
+                .get::<String>("jimmy")
+                .unwrap()
+        );
+        assert_eq!(
+            "hello",
+            Table::existing("posts", &copy)
+                .get::<String>("first")
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{span, subscriber, Event, Metadata, Subscriber};
+
+        struct SpanRecorder(Arc<Mutex<Vec<String>>>);
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                let mut names = self.0.lock().unwrap();
+                names.push(span.metadata().name().to_owned());
+                span::Id::from_u64(names.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let recorder = SpanRecorder(spans.clone());
+
+        subscriber::with_default(recorder, || {
+            let conn = Connection::open_in_memory().unwrap();
+            let table = Table::new("users");
+            let db = table.create(&conn).unwrap();
+
+            db.set("jimmy", "123").unwrap();
+        });
+
+        let spans = spans.lock().unwrap();
+        assert!(spans.contains(&"create".to_owned()));
+        assert!(spans.contains(&"set".to_owned()));
+    }
+
+    #[test]
+    fn test_get_checked_matching_tag() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", &123).unwrap();
+        db.set("bob", "abc").unwrap();
+
+        assert_eq!(123, db.get_checked::<i32>("jimmy", "integer").unwrap());
+        assert_eq!("abc", db.get_checked::<String>("bob", "text").unwrap());
+    }
+
+    #[test]
+    fn test_get_checked_mismatched_tag() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "123").unwrap();
+
+        let result = db.get_checked::<i32>("jimmy", "integer");
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidColumnType(_, _, rusqlite::types::Type::Text))
+        ));
+    }
+
+    #[test]
+    fn test_remove_returning() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "bean").unwrap();
+
+        let removed = db.remove_returning::<String>("jimmy").unwrap();
+        assert_eq!(Some("bean".to_owned()), removed);
+
+        let jimmy = db.get::<String>("jimmy");
+        assert!(jimmy.is_err());
+    }
+
+    #[test]
+    fn test_remove_returning_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let removed = db.remove_returning::<String>("unknown").unwrap();
+        assert_eq!(None, removed);
+    }
+
+    #[test]
+    fn test_page_after() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        for i in 0..25 {
+            db.set(&format!("user{:02}", i), &i).unwrap();
+        }
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut after: Option<String> = None;
+
+        loop {
+            let page = db.page_after::<i32>(after.as_deref(), 10).unwrap();
+
+            if page.is_empty() {
+                break;
+            }
+
+            assert!(page.len() <= 10);
+            after = page.last().map(|entry| entry.key.clone());
+            seen.extend(page.into_iter().map(|entry| entry.key));
+        }
+
+        let expected: Vec<String> = (0..25).map(|i| format!("user{:02}", i)).collect();
+        assert_eq!(expected, seen);
+    }
+
+    #[test]
+    fn test_page_after_empty_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let page = db.page_after::<String>(None, 10).unwrap();
+        assert!(page.is_empty());
+    }
+
+    #[test]
+    fn test_remove_one() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "bean").unwrap();
+
+        assert!(db.remove_one("jimmy").unwrap());
+        assert!(db.get::<String>("jimmy").is_err());
+    }
+
+    #[test]
+    fn test_remove_one_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert!(!db.remove_one("unknown").unwrap());
+    }
+
+    #[test]
+    fn test_map_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("counters");
+        let db = table.create(&conn).unwrap();
+        db.set("visits", &21).unwrap();
+
+        let result = db.map_value("visits", |visits: i32| visits * 2).unwrap();
+        assert_eq!(Some(42), result);
+
+        assert_eq!(42, db.get::<i32>("visits").unwrap());
+    }
+
+    #[test]
+    fn test_map_value_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("counters");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.map_value("visits", |visits: i32| visits * 2).unwrap();
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn test_is_compatible() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+
+        assert!(Table::is_compatible("users", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_is_compatible_foreign_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT)",
+            (),
+        )
+        .unwrap();
+
+        assert!(!Table::is_compatible("users", &conn).unwrap());
+        assert!(!Table::is_compatible("missing", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_verify_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+
+        assert!(Table::existing("users", &conn).verify_schema().is_ok());
+    }
+
+    #[test]
+    fn test_verify_schema_incompatible_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE emails (id INTEGER PRIMARY KEY, email TEXT);
+             CREATE TABLE nullable (k TEXT, v);
+             CREATE TABLE keys (k TEXT NOT NULL);",
+        )
+        .unwrap();
+
+        let message = |name: &str| match Table::existing(name, &conn).verify_schema() {
+            Err(Error::SqliteFailure(_, Some(message))) => message,
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert_eq!(
+            "incompatible table emails: missing key column k",
+            message("emails")
+        );
+        assert_eq!(
+            "incompatible table nullable: key column k allows NULL",
+            message("nullable")
+        );
+        assert_eq!(
+            "incompatible table keys: missing value column v",
+            message("keys")
+        );
+        assert_eq!("no such table: missing", message("missing"));
+    }
+
+    #[test]
+    fn test_str_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = table.create(&conn).unwrap();
+
+        table.set_str("jimmy", "abc").unwrap();
+
+        let result = table.get_str("jimmy");
+
+        assert!(result.is_ok());
+        assert_eq!("abc", result.unwrap());
+    }
+
+    #[test]
+    fn test_get_str_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = table.create(&conn).unwrap();
+
+        let result = table.get_str("jimmy");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_existing_or_create() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::existing_or_create("users", &conn).unwrap();
+
+        let result = table.set("jimmy", "abc");
+        assert!(result.is_ok());
+
+        // Calling it again on the now existing table keeps the data
+        let table = Table::existing_or_create("users", &conn).unwrap();
+        assert_eq!("abc", table.get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        db.set("bob", "def").unwrap();
+
+        let since = db.last_modified("bob").unwrap().unwrap();
+
+        // Timestamps have a resolution of one second
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+
+        db.set("sally", "ghi").unwrap();
+        db.update("jimmy", "jkl").unwrap();
+
+        let changes = db.changed_since::<String>(since).unwrap();
+
+        assert_eq!(
+            vec![
+                KeyValue::from(("jimmy".to_owned(), "jkl".to_owned())),
+                KeyValue::from(("sally".to_owned(), "ghi".to_owned()))
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn test_format_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        table.create(&conn).unwrap();
+
+        assert_eq!(FORMAT_VERSION, table.format_version(&conn).unwrap());
+    }
+
+    #[test]
+    fn test_format_version_v1_and_missing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE legacy (k varchar(255) PRIMARY KEY, v)", ())
+            .unwrap();
+
+        assert_eq!(1, Table::new("legacy").format_version(&conn).unwrap());
+        assert!(Table::new("missing").format_version(&conn).is_err());
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE legacy (k varchar(255) PRIMARY KEY, v);
+             INSERT INTO legacy (k, v) VALUES ('jimmy', 'abc'), ('bob', 123);",
+        )
+        .unwrap();
+
+        let table = Table::new("legacy");
+        table.migrate_from_v1(&conn).unwrap();
+
+        let columns = table_columns("legacy", &conn).unwrap();
+        assert_eq!(
+            vec!["k", "v", "created_at", "updated_at"],
+            columns.iter().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert_eq!(2, table.format_version(&conn).unwrap());
+
+        let db = Table::existing("legacy", &conn);
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+        assert_eq!(123, db.get::<i64>("bob").unwrap());
+        assert!(db.last_modified("jimmy").unwrap().unwrap() > 0);
+
+        db.set("sally", "def").unwrap();
+        assert!(db.set("jimmy", "duplicate").is_err());
+        assert!(db.last_modified("sally").unwrap().unwrap() > 0);
+
+        // Migrating again leaves the table as is
+        table.migrate_from_v1(&conn).unwrap();
+        assert_eq!(3, db.count().unwrap());
+    }
+
+    #[test]
+    fn test_create_upgrades_v1_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE legacy (k varchar(255) PRIMARY KEY UNIQUE NOT NULL, v);
+             INSERT INTO legacy (k, v) VALUES ('jimmy', 'abc');",
+        )
+        .unwrap();
+
+        let table = Table::new("legacy");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(FORMAT_VERSION, table.format_version(&conn).unwrap());
+        assert_eq!(1, db.update("jimmy", "def").unwrap());
+        assert_eq!("def", db.get::<String>("jimmy").unwrap());
+        assert!(db.last_modified("jimmy").unwrap().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_migrate_from_v1_keeps_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE legacy (k varchar(255) PRIMARY KEY, v, owner TEXT);
+             CREATE INDEX legacy_owner ON legacy (owner);
+             CREATE TABLE audit (k TEXT);
+             CREATE TRIGGER legacy_audit AFTER INSERT ON legacy BEGIN
+                 INSERT INTO audit (k) VALUES (new.k);
+             END;
+             INSERT INTO legacy (rowid, k, v, owner) VALUES (7, 'jimmy', 'abc', 'admin');",
+        )
+        .unwrap();
+
+        let table = Table::new("legacy");
+        table.migrate_from_v1(&conn).unwrap();
+
+        let db = Table::existing("legacy", &conn);
+        assert_eq!(
+            Some((7, "abc".to_owned())),
+            db.get_with_rowid::<String>("jimmy").unwrap()
+        );
+
+        let owner: String = conn
+            .query_row("SELECT owner FROM legacy WHERE k = 'jimmy'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!("admin", owner);
+
+        db.set("bob", "def").unwrap();
+        let audited: i64 = conn
+            .query_row("SELECT COUNT(*) FROM audit", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(2, audited);
+
+        let objects: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE tbl_name = 'legacy' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                "legacy",
+                "legacy_audit",
+                "legacy_owner",
+                "legacy_timestamps",
+                "sqlite_autoindex_legacy_1"
+            ],
+            objects
+        );
+    }
+
+    #[test]
+    fn test_migrate_from_v1_missing_table() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        assert!(Table::new("missing").migrate_from_v1(&conn).is_err());
+    }
+
+    #[test]
+    fn test_try_new_valid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::try_new("users").unwrap();
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_try_new_invalid() {
+        match Table::try_new("users; DROP TABLE users") {
+            Err(Error::SqliteFailure(_, Some(message))) => {
+                assert_eq!("invalid identifier: \"users; DROP TABLE users\"", message)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(Table::try_new("").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_identifier_valid() {
+        for name in ["users", "_private", "user_emails", "Users2", "t"] {
+            assert_eq!(name, sanitize_identifier(name).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sanitize_identifier_invalid() {
+        let names = [
+            "",
+            "1users",
+            "users; DROP TABLE users",
+            "users--",
+            "\"users\"",
+            "'users'",
+            "[users]",
+            "`users`",
+            "user emails",
+            "users\n",
+            "users\0",
+            "usérs",
+            "用户",
+            "sqlite_master",
+            "SQLITE_users",
+        ];
+
+        for name in names {
+            assert!(
+                sanitize_identifier(name).is_err(),
+                "{:?} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_sanitize_identifier_characters() {
+        // Every accepted name must only contain characters safe to interpolate
+        for c in (0..0x3000).filter_map(char::from_u32) {
+            let name = format!("a{}", c);
+
+            if sanitize_identifier(&name).is_ok() {
+                assert!(
+                    c.is_ascii_alphanumeric() || c == '_',
+                    "{:?} was accepted",
+                    c
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_invalid_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users; DROP TABLE users");
+
+        assert!(table.create(&conn).is_err());
+    }
+
+    #[test]
+    fn test_get_or_else_existing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let mut called = false;
+        let result = db
+            .get_or_else("jimmy", || {
+                called = true;
+                "default".to_owned()
+            })
+            .unwrap();
+
+        assert_eq!("abc", result);
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_get_or_else_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.get_or_else("jimmy", || "default".to_owned()).unwrap();
+
+        assert_eq!("default", result);
+    }
+
+    #[test]
+    fn test_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        db.set("bob", "def").unwrap();
+
+        assert_eq!(
+            vec!["bob".to_owned(), "jimmy".to_owned()],
+            db.keys().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keys_iter() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        // Enough keys to span several chunks
+        for i in 0..(KEYS_ITER_CHUNK_SIZE * 2 + 5) {
+            db.set(&format!("user{}", i), &i.to_string()).unwrap();
+        }
+
+        let keys = db
+            .keys_iter()
+            .unwrap()
+            .collect::<Result<Vec<String>, Error>>()
+            .unwrap();
+
+        assert_eq!(db.keys().unwrap(), keys);
+    }
+
+    #[test]
+    fn test_keys_iter_duplicate_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events").allow_duplicate_keys();
+        let db = table.create(&conn).unwrap();
+
+        // Copies of one key spanning several chunks
+        for i in 0..(KEYS_ITER_CHUNK_SIZE + 50) {
+            db.set("jimmy", &(i as i64)).unwrap();
+        }
+        db.set("bob", "abc").unwrap();
+        db.set("sally", "def").unwrap();
+
+        let keys = db
+            .keys_iter()
+            .unwrap()
+            .collect::<Result<Vec<String>, Error>>()
+            .unwrap();
+
+        assert_eq!(KEYS_ITER_CHUNK_SIZE + 52, keys.len());
+        assert_eq!(db.keys().unwrap(), keys);
+    }
+
+    #[test]
+    fn test_keys_iter_without_rowid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users").without_rowid();
+        let db = table.create(&conn).unwrap();
+
+        for i in 0..(KEYS_ITER_CHUNK_SIZE + 5) {
+            db.set(&format!("user{:03}", i), &(i as i64)).unwrap();
+        }
+
+        assert_eq!(KEYS_ITER_CHUNK_SIZE + 5, db.keys_iter().unwrap().count());
+    }
+
+    #[test]
+    fn test_keys_iter_missing_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let db = Table::existing("missing", &conn);
+
+        assert!(db.keys_iter().is_err());
+    }
+
+    #[test]
+    fn test_allow_duplicate_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events").allow_duplicate_keys();
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "signed up").unwrap();
+        db.set("jimmy", "logged in").unwrap();
+        db.set("bob", "signed up").unwrap();
+        db.set("jimmy", "logged out").unwrap();
+
+        let events = db.get_all_for_key::<String>("jimmy").unwrap();
+        assert_eq!(vec!["signed up", "logged in", "logged out"], events);
+
+        assert_eq!("signed up", db.get::<String>("jimmy").unwrap());
+        assert!(Table::is_compatible("events", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_append_and_read_log() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events").allow_duplicate_keys();
+        let db = table.create(&conn).unwrap();
+
+        let created = db.append("order-1", "created").unwrap();
+        db.append("order-2", "created").unwrap();
+        let paid = db.append("order-1", "paid").unwrap();
+        let shipped = db.append("order-1", "shipped").unwrap();
+
+        assert!(created < paid && paid < shipped);
+        assert_eq!(
+            vec!["created", "paid", "shipped"],
+            db.read_log::<String>("order-1").unwrap()
+        );
+        assert_eq!(vec!["created"], db.read_log::<String>("order-2").unwrap());
+        assert!(db.read_log::<String>("order-3").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_append_unique_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events");
+        let db = table.create(&conn).unwrap();
+
+        assert!(db.append("order-1", "created").is_ok());
+        assert!(db.append("order-1", "paid").is_err());
+    }
+
+    #[test]
+    fn test_get_all_for_key_unique_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        assert!(db.set("jimmy", "def").is_err());
+
+        assert_eq!(vec!["abc"], db.get_all_for_key::<String>("jimmy").unwrap());
+        assert!(db.get_all_for_key::<String>("bob").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("scores");
+        let db = table.create(&conn).unwrap();
+
+        db.set("a", &4).unwrap();
+        db.set("b", &-2).unwrap();
+        db.set("c", &10).unwrap();
+        db.set("d", &0.5).unwrap();
+        db.set("e", "100").unwrap();
+
+        assert_eq!(12.5, db.sum().unwrap());
+        assert_eq!(Some(3.125), db.avg().unwrap());
+        assert_eq!(Some(-2.0), db.min().unwrap());
+        assert_eq!(Some(10.0), db.max().unwrap());
+    }
+
+    #[test]
+    fn test_aggregates_empty_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("scores");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(0.0, db.sum().unwrap());
+        assert_eq!(None, db.avg().unwrap());
+        assert_eq!(None, db.min().unwrap());
+        assert_eq!(None, db.max().unwrap());
+    }
+
+    #[test]
+    fn test_get_as() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("number", &42).unwrap();
+        db.set("text", "abc").unwrap();
+
+        let number = db.get_as::<i64, String>("number").unwrap();
+        assert_eq!(Either::Left(42), number);
+
+        let text = db.get_as::<i64, String>("text").unwrap();
+        assert_eq!(Either::Right("abc".to_owned()), text);
+    }
+
+    #[test]
+    fn test_get_as_no_matching_type() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("text", "abc").unwrap();
+
+        assert!(db.get_as::<i64, f64>("text").is_err());
+        assert!(db.get_as::<i64, String>("missing").is_err());
+    }
+
+    #[test]
+    fn test_rename_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let renamed = db.rename_table("customers").unwrap();
+
+        assert_eq!("abc", renamed.get::<String>("jimmy").unwrap());
+        assert!(!Table::is_compatible("users", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_rename_table_invalid_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert!(db.rename_table("customers; DROP TABLE users").is_err());
+        assert!(Table::is_compatible("users", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_sweep_prefix() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache");
+        let db = table.create(&conn).unwrap();
+
+        db.set("cache:user:1", "jimmy").unwrap();
+        db.set("cache:user:2", "bob").unwrap();
+        db.set("cache:post:1", "hello").unwrap();
+        db.set("CACHE:user:3", "sally").unwrap();
+        db.set("other", "value").unwrap();
+
+        assert_eq!(2, db.sweep_prefix("cache:user:").unwrap());
+        assert_eq!(
+            vec!["CACHE:user:3", "cache:post:1", "other"],
+            db.keys().unwrap()
+        );
+
+        assert_eq!(1, db.sweep_prefix("cache:").unwrap());
+        assert_eq!(0, db.sweep_prefix("cache:").unwrap());
+        assert_eq!(vec!["CACHE:user:3", "other"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_sweep_prefix_wildcards() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache");
+        let db = table.create(&conn).unwrap();
+
+        db.set("100%_off", "a").unwrap();
+        db.set("100x_off", "b").unwrap();
+
+        assert_eq!(1, db.sweep_prefix("100%").unwrap());
+        assert_eq!(vec!["100x_off"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_pragmas() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users")
+            .synchronous(Synchronous::Off)
+            .cache_size(1234)
+            .mmap_size(0);
+        table.create(&conn).unwrap();
+
+        let cache_size: i64 = conn
+            .query_row("PRAGMA cache_size", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(1234, cache_size);
+
+        let synchronous: i64 = conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, synchronous);
+    }
+
+    #[test]
+    fn test_backup_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup_to_file.sqlite");
+
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+        db.set("bob", &123).unwrap();
+
+        db.backup_to_file(&path).unwrap();
+
+        // Backing up again replaces the previous copy
+        db.set("sally", "def").unwrap();
+        db.backup_to_file(&path).unwrap();
+
+        let backup = Connection::open(&path).unwrap();
+        let restored = Table::existing("users", &backup);
+
+        assert_eq!(vec!["bob", "jimmy", "sally"], restored.keys().unwrap());
+        assert_eq!("abc", restored.get::<String>("jimmy").unwrap());
+        assert_eq!(123, restored.get::<i32>("bob").unwrap());
+        assert!(restored.set("jimmy", "duplicate").is_err());
+    }
+
+    #[test]
+    fn test_get_with_rowid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let (rowid, value) = db.get_with_rowid::<String>("jimmy").unwrap().unwrap();
+
+        assert!(rowid > 0);
+        assert_eq!("abc", value);
+        assert_eq!(Some("jimmy".to_owned()), db.key_for_rowid(rowid).unwrap());
+    }
+
+    #[test]
+    fn test_get_with_rowid_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(None, db.get_with_rowid::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_set_many() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.set_many(&[("jimmy", "abc"), ("bob", "def")]);
+
+        assert_eq!(2, result.unwrap());
+        assert_eq!("def", db.get::<String>("bob").unwrap());
+    }
+
+    #[test]
+    fn test_batches_larger_than_chunk_size() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("numbers");
+        let db = table.create(&conn).unwrap();
+
+        let keys = (0..5000)
+            .map(|i| format!("key{:04}", i))
+            .collect::<Vec<_>>();
+        let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+        let items = keys
+            .iter()
+            .zip(0..)
+            .map(|(key, i)| (*key, i))
+            .collect::<Vec<_>>();
+
+        assert_eq!(5000, db.set_many(&items).unwrap());
+        assert_eq!(5000, db.count().unwrap());
+
+        let entries = db.get_many::<i64>(&keys).unwrap();
+        assert_eq!(5000, entries.len());
+        assert!(entries
+            .iter()
+            .zip(0..)
+            .all(|(entry, i)| entry.key == keys[i as usize] && entry.value == i));
+
+        assert_eq!(4000, db.remove_many(&keys[..4000]).unwrap());
+        assert_eq!(1000, db.count().unwrap());
+        assert_eq!(1000, db.get_many::<i64>(&keys).unwrap().len());
+    }
+
+    #[test]
+    fn test_set_many_rolls_back_every_chunk() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("numbers");
+        let db = table.create(&conn).unwrap();
+        db.set("key4999", &0).unwrap();
+
+        let keys = (0..5000).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+        let items = keys.iter().map(|key| (key.as_str(), 1)).collect::<Vec<_>>();
+
+        assert!(db.set_many(&items).is_err());
+        assert_eq!(1, db.count().unwrap());
+    }
+
+    #[test]
+    fn test_get_many_missing_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set_many(&[("jimmy", "abc"), ("bob", "def")]).unwrap();
+
+        let entries = db
+            .get_many::<String>(&["sally", "jimmy", "bob", "jimmy"])
+            .unwrap();
+        let keys = entries
+            .iter()
+            .map(|entry| entry.key.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["jimmy", "bob"], keys);
+        assert!(db.get_many::<String>(&[]).unwrap().is_empty());
+        assert_eq!(0, db.remove_many(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_set_iter() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("squares");
+        let db = table.create(&conn).unwrap();
+
+        let result = db.set_iter((0..10_000).map(|i: i64| (format!("key{}", i), i * i)));
+
+        assert_eq!(10_000, result.unwrap());
+        assert_eq!(99_980_001, db.get::<i64>("key9999").unwrap());
+    }
+
+    #[test]
+    fn test_set_iter_rolls_back_on_failure() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let items = vec![("jimmy", "abc"), ("bob", "def"), ("jimmy", "ghi")];
+        assert!(db.set_iter(items).is_err());
+
+        assert!(db.keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let sql = db.describe().unwrap();
+
+        assert!(sql.starts_with("CREATE TABLE users"));
+        assert!(sql.contains("k varchar(255) PRIMARY KEY UNIQUE NOT NULL"));
+        assert!(sql.contains("v,"));
+        assert!(sql.contains(UPDATED_AT_COLUMN));
+    }
+
+    #[test]
+    fn test_describe_missing_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let db = Table::existing("missing", &conn);
+
+        assert!(db.describe().is_err());
+    }
+
+    #[test]
+    fn test_key_value_fields() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", &1).unwrap();
+        db.set("bob", &2).unwrap();
+
+        let page = db.page_after::<i32>(None, 10).unwrap();
+
+        assert_eq!("bob", page[0].key);
+        assert_eq!(2, page[0].value);
+        assert_eq!("jimmy", page[1].key);
+        assert_eq!(1, page[1].value);
+
+        let entry = KeyValue::from(("sally".to_owned(), 3));
+        assert_eq!(("sally".to_owned(), 3), entry.into());
+    }
+
+    #[test]
+    fn test_clear_all() {
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        let sessions = Table::new("sessions");
+        let sessions = sessions.create(&conn).unwrap();
+        let other = Table::new("other");
+        let other = other.create(&conn).unwrap();
+
+        users.set_many(&[("jimmy", 1), ("bob", 2)]).unwrap();
+        sessions.set_many(&[("a", 1), ("b", 2), ("c", 3)]).unwrap();
+        other.set("keep", &1).unwrap();
+
+        let removed = Table::clear_all(&conn, &["users", "sessions"]).unwrap();
+
+        assert_eq!(5, removed);
+        assert!(users.keys().unwrap().is_empty());
+        assert!(sessions.keys().unwrap().is_empty());
+        assert_eq!(vec!["keep"], other.keys().unwrap());
+    }
+
+    #[test]
+    fn test_drop_all() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+        Table::new("sessions").create(&conn).unwrap();
+        Table::new("events")
+            .allow_duplicate_keys()
+            .create(&conn)
+            .unwrap();
+        conn.execute(
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, email TEXT)",
+            (),
+        )
+        .unwrap();
+
+        assert_eq!(3, Table::drop_all(&conn).unwrap());
+
+        assert!(!Table::is_compatible("users", &conn).unwrap());
+        assert!(!Table::is_compatible("sessions", &conn).unwrap());
+        assert!(!Table::is_compatible("events", &conn).unwrap());
+        assert_eq!(0, Table::drop_all(&conn).unwrap());
+
+        let accounts: i64 = conn
+            .query_row("SELECT count(*) FROM accounts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, accounts);
+    }
+
+    #[test]
+    fn test_clear_all_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        users.set("jimmy", &1).unwrap();
+
+        assert!(Table::clear_all(&conn, &["users", "users; DROP TABLE users"]).is_err());
+        assert!(Table::clear_all(&conn, &["users", "missing"]).is_err());
+
+        assert_eq!(vec!["jimmy"], users.keys().unwrap());
+    }
+
+    #[test]
+    fn test_count() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(0, db.count().unwrap());
+
+        db.set_many(&[("jimmy", 1), ("bob", 2)]).unwrap();
+
+        assert_eq!(2, db.count().unwrap());
+    }
+
+    /// Counts the statements currently prepared on the connection
+    fn prepared_statements(conn: &Connection) -> usize {
+        let mut count = 0;
+
+        unsafe {
+            let db = conn.handle();
+            let mut stmt = ffi::sqlite3_next_stmt(db, std::ptr::null_mut());
+            while !stmt.is_null() {
+                count += 1;
+                stmt = ffi::sqlite3_next_stmt(db, stmt);
+            }
+        }
+
+        count
     }
 
     #[test]
-    fn test_getting_missing_key() {
+    fn test_statements_are_reused() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
-        let table = table.create(&conn).unwrap();
+        let db = table.create(&conn).unwrap();
+        conn.flush_prepared_statement_cache();
 
-        let result = table.get::<String>("jimmy");
+        for i in 0..100 {
+            let key = format!("key-{}", i);
+            db.set(&key, &i).unwrap();
+            db.update(&key, &(i * 2)).unwrap();
+            assert_eq!(i * 2, db.get::<i32>(&key).unwrap());
+        }
+        db.remove("key-0").unwrap();
+        db.remove("key-1").unwrap();
 
-        assert_eq!(true, result.is_err());
+        // One statement each for set, update, get and remove
+        assert_eq!(4, prepared_statements(&conn));
+        assert_eq!(98, db.count().unwrap());
     }
 
     #[test]
-    fn test_getting_valid_key() {
+    fn test_require() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
-        let table = table.create(&conn).unwrap();
+        table.create(&conn).unwrap();
 
-        table.set("jimmy", "abc").unwrap();
+        let db = Table::require("users", &conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
 
-        let result = table.get::<String>("jimmy");
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+    }
 
-        assert_eq!(true, result.is_ok());
-        assert_eq!("abc", result.unwrap());
+    #[test]
+    fn test_require_missing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE foreign_table (id INTEGER)", ())
+            .unwrap();
+
+        match Table::require("users", &conn) {
+            Err(Error::SqliteFailure(_, Some(message))) => {
+                assert_eq!("no such table: users", message)
+            }
+            other => panic!("expected no such table, got {:?}", other),
+        }
+        assert!(Table::require("foreign_table", &conn).is_err());
     }
 
     #[test]
-    fn test_casting_results() {
+    fn test_floats_round_trip() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
-        let table = table.create(&conn).unwrap();
+        let db = table.create(&conn).unwrap();
 
-        table.set("jim", "123").unwrap();
-        table.set("jimmy", &123).unwrap();
-        table.set("bob", &true).unwrap();
+        let values = [
+            2.75_f64,
+            -0.0,
+            f64::MIN_POSITIVE,
+            f64::EPSILON,
+            1e-300,
+            1e300,
+            f64::MAX,
+            f64::MIN,
+            0.1 + 0.2,
+        ];
 
-        let result = table.get::<i32>("jimmy");
-        assert_eq!(123, result.unwrap());
+        for (i, value) in values.iter().enumerate() {
+            let key = format!("float-{}", i);
+            db.set_float(&key, *value).unwrap();
 
-        let result = table.get::<String>("jim");
-        assert_eq!("123", result.unwrap());
+            let kind: String = conn
+                .query_row("SELECT typeof(v) FROM users WHERE k = ?1", [&key], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!("real", kind);
+            assert_eq!(value.to_bits(), db.get_float(&key).unwrap().to_bits());
+            assert_eq!(value.to_bits(), db.get::<f64>(&key).unwrap().to_bits());
+        }
 
-        let result = table.get::<bool>("bob");
-        assert_eq!(true, result.unwrap());
+        db.set("f32", &1.5e-30_f32).unwrap();
+        assert_eq!(1.5e-30_f32, db.get::<f32>("f32").unwrap());
+
+        db.set("int", &3).unwrap();
+        assert_eq!(3.0, db.get_float("int").unwrap());
     }
 
     #[test]
-    fn test_removing_by_key() {
+    fn test_touch() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
         let db = table.create(&conn).unwrap();
-        db.set("jimmy", "bean").unwrap();
 
-        let jimmy = db.get::<String>("jimmy").unwrap();
-        assert_eq!(jimmy, "bean");
+        db.set("jimmy", "abc").unwrap();
+        conn.execute("UPDATE users SET updated_at = 1000", ())
+            .unwrap();
 
-        db.remove("jimmy").unwrap();
+        assert!(db.touch("jimmy").unwrap());
+        assert!(!db.touch("bob").unwrap());
 
-        let jimmy = db.get::<String>("jimmy");
-        assert_eq!(true, jimmy.is_err());
+        assert!(db.last_modified("jimmy").unwrap().unwrap() > 1000);
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
     }
 
     #[test]
-    fn test_removing_by_missing_key() {
+    fn test_search_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("posts");
+        let db = table.create(&conn).unwrap();
+
+        db.set("c", "The quick brown fox").unwrap();
+        db.set("a", "a BROWN dog").unwrap();
+        db.set("b", "a grey cat").unwrap();
+        db.set("d", "100% brown_ish").unwrap();
+
+        let entries: Vec<(String, String)> = db
+            .search_values::<String>("brown")
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("a".to_owned(), "a BROWN dog".to_owned()),
+                ("c".to_owned(), "The quick brown fox".to_owned()),
+                ("d".to_owned(), "100% brown_ish".to_owned()),
+            ],
+            entries
+        );
+        assert!(db.search_values::<String>("zebra").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_values_escapes_wildcards() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("posts");
+        let db = table.create(&conn).unwrap();
+
+        db.set("a", "100% sure").unwrap();
+        db.set("b", "100 percent").unwrap();
+        db.set("c", "snake_case").unwrap();
+        db.set("d", "snakeXcase").unwrap();
+        db.set("e", "back\\slash").unwrap();
+
+        let keys = |needle: &str| -> Vec<String> {
+            db.search_values::<String>(needle)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.key)
+                .collect()
+        };
+
+        assert_eq!(vec!["a"], keys("0%"));
+        assert_eq!(vec!["c"], keys("e_c"));
+        assert_eq!(vec!["e"], keys("k\\s"));
+        assert_eq!(5, keys("").len());
+    }
+
+    #[test]
+    fn test_scan_prefix_limit() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("posts");
+        let db = table.create(&conn).unwrap();
+
+        for i in (0..10).rev() {
+            db.set(&format!("post:{}", i), &i).unwrap();
+        }
+        db.set("draft:0", &0).unwrap();
+
+        let entries: Vec<(String, i64)> = db
+            .scan_prefix_limit::<i64>("post:", 3)
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("post:0".to_owned(), 0),
+                ("post:1".to_owned(), 1),
+                ("post:2".to_owned(), 2),
+            ],
+            entries
+        );
+        assert_eq!(10, db.scan_prefix_limit::<i64>("post:", 50).unwrap().len());
+        assert!(db.scan_prefix_limit::<i64>("post:", 0).unwrap().is_empty());
+        assert_eq!(1, db.scan_prefix_limit::<i64>("draft:", 3).unwrap().len());
+    }
+
+    #[test]
+    fn test_scan_prefix_stripped() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
         let db = table.create(&conn).unwrap();
 
-        let result = db.remove("unknown");
+        db.set("user:2", "bob").unwrap();
+        db.set("user:1", "jimmy").unwrap();
+        db.set("user:", "empty").unwrap();
+        db.set("post:1", "hello").unwrap();
+        db.set("USER:3", "shouty").unwrap();
 
-        assert_eq!(true, result.is_ok());
-        assert_eq!(0, result.unwrap());
+        let entries: Vec<(String, String)> = db
+            .scan_prefix_stripped::<String>("user:")
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("".to_owned(), "empty".to_owned()),
+                ("1".to_owned(), "jimmy".to_owned()),
+                ("2".to_owned(), "bob".to_owned()),
+            ],
+            entries
+        );
     }
 
     #[test]
-    fn test_updating_existing_value() {
+    fn test_sample() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
         let db = table.create(&conn).unwrap();
 
-        db.set("jimmy", "123").unwrap();
+        assert!(db.sample::<i32>(3).unwrap().is_empty());
 
-        let result = db.update("jimmy", "456");
+        db.set_many(&[("a", 1), ("b", 2), ("c", 3), ("d", 4), ("e", 5)])
+            .unwrap();
+
+        for n in [0, 1, 3, 5, 10] {
+            let sample = db.sample::<i32>(n).unwrap();
+
+            assert_eq!(n.min(5), sample.len());
+            for entry in sample {
+                assert_eq!(entry.value, db.get::<i32>(&entry.key).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_default_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open_default.sqlite");
+
+        assert_eq!(OsString::from(DEFAULT_PATH), path_from(None));
+        assert_eq!(
+            path.as_os_str(),
+            path_from(Some(path.clone().into_os_string()))
+        );
+
+        let conn = Connection::open(path_from(Some(path.clone().into_os_string()))).unwrap();
+        Table::new("users").create(&conn).unwrap();
+        drop(conn);
+
+        let conn = Connection::open(&path).unwrap();
+        assert!(Table::is_compatible("users", &conn).unwrap());
+    }
+
+    #[test]
+    fn test_get_or_default() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(0, db.get_or_default::<i32>("visits").unwrap());
+        assert!(!db.get_or_default::<bool>("enabled").unwrap());
+
+        db.set("visits", &3).unwrap();
+        db.set("name", "jimmy").unwrap();
+
+        assert_eq!(3, db.get_or_default::<i32>("visits").unwrap());
+        assert!(db.get_or_default::<i32>("name").is_err());
+    }
+
+    #[test]
+    fn test_remove_where_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[("jimmy", 1), ("jane", 2), ("bob", 3), ("sally", 4)])
+            .unwrap();
+
+        assert_eq!(2, db.remove_where_key(|key| key.starts_with('j')).unwrap());
+        assert_eq!(vec!["bob", "sally"], db.keys().unwrap());
+        assert_eq!(0, db.remove_where_key(|key| key.starts_with('j')).unwrap());
+    }
+
+    #[test]
+    fn test_create_sql() {
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS users (
+    k varchar(255) PRIMARY KEY UNIQUE NOT NULL,
+    v,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+)",
+            Table::new("users").create_sql()
+        );
+
+        let sql = Table::new("events").allow_duplicate_keys().create_sql();
+        assert!(sql.contains("k varchar(255) NOT NULL,"));
+        assert!(!sql.contains("PRIMARY KEY"));
+    }
+
+    #[test]
+    fn test_create_sql_matches_create() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(
+            table.create_sql().replace("IF NOT EXISTS ", ""),
+            db.describe().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_remove_if_matching() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        db.set("count", &1).unwrap();
+
+        assert!(db.remove_if("jimmy", "abc").unwrap());
+        assert!(db.remove_if("count", &1).unwrap());
+        assert!(db.keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove_if_mismatched() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        db.set("count", &1).unwrap();
+
+        assert!(!db.remove_if("jimmy", "def").unwrap());
+        assert!(!db.remove_if("count", "1").unwrap());
+        assert!(!db.remove_if("missing", "abc").unwrap());
+        assert_eq!(vec!["count", "jimmy"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_set_if_changed_same_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("config");
+        let db = table.create(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO config (k, v, updated_at) VALUES ('retries', 3, 0)",
+            (),
+        )
+        .unwrap();
+
+        assert!(!db.set_if_changed("retries", &3).unwrap());
+        assert_eq!(Some(0), db.last_modified("retries").unwrap());
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_set_if_changed_different_value() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("config");
+        let db = table.create(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO config (k, v, updated_at) VALUES ('retries', 3, 0)",
+            (),
+        )
+        .unwrap();
+
+        assert!(db.set_if_changed("retries", &5).unwrap());
+        assert_eq!(5, db.get::<i64>("retries").unwrap());
+        assert!(db.last_modified("retries").unwrap().unwrap() > 0);
+
+        assert!(db.set_if_changed("retries", &"five".to_owned()).unwrap());
+        assert_eq!("five", db.get::<String>("retries").unwrap());
+
+        assert!(db.set_if_changed("timeout", &30).unwrap());
+        assert_eq!(30, db.get::<i64>("timeout").unwrap());
+    }
+
+    #[test]
+    fn test_iter_prefix() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        // Spans several chunks
+        let keys = (0..250)
+            .map(|i| format!("user:{:03}", i))
+            .collect::<Vec<_>>();
+        db.set_iter(keys.iter().map(|key| (key, 1))).unwrap();
+        db.set_many(&[("post:1", 2), ("users", 3), ("USER:1", 4)])
+            .unwrap();
+
+        let entries = db
+            .iter_prefix::<i32>("user:")
+            .unwrap()
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+
+        assert_eq!(250, entries.len());
+        assert_eq!("user:000", entries[0].key);
+        assert_eq!("user:249", entries[249].key);
+        assert!(entries.iter().all(|entry| entry.value == 1));
+
+        assert_eq!(0, db.iter_prefix::<i32>("missing:").unwrap().count());
+    }
+
+    #[test]
+    fn test_iter_prefix_duplicate_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events").allow_duplicate_keys();
+        let db = table.create(&conn).unwrap();
+
+        // Copies of one key spanning several chunks
+        for i in 0..(KEYS_ITER_CHUNK_SIZE + 50) {
+            db.set("user:jimmy", &(i as i64)).unwrap();
+        }
+        db.set("user:bob", &-1).unwrap();
+        db.set("post:1", &-1).unwrap();
+
+        let entries = db
+            .iter_prefix::<i64>("user:")
+            .unwrap()
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+
+        assert_eq!(KEYS_ITER_CHUNK_SIZE + 51, entries.len());
+        assert_eq!("user:bob", entries[0].key);
+        assert_eq!(
+            (0..(KEYS_ITER_CHUNK_SIZE + 50) as i64).collect::<Vec<_>>(),
+            entries[1..]
+                .iter()
+                .map(|entry| entry.value)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_without_rowid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users").without_rowid();
+        let db = table.create(&conn).unwrap();
+
+        assert!(db.describe().unwrap().ends_with("WITHOUT ROWID"));
+
+        db.set("jimmy", "abc").unwrap();
+        db.set("bob", "def").unwrap();
+        db.update("bob", "ghi").unwrap();
+
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+        assert_eq!("ghi", db.get::<String>("bob").unwrap());
+        assert!(db.set("jimmy", "again").is_err());
+        assert_eq!(1, db.remove("jimmy").unwrap());
+        assert_eq!(vec!["bob"], db.keys().unwrap());
+        assert!(conn
+            .query_row("SELECT rowid FROM users", [], |row| row
+                .get::<usize, i64>(0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_without_rowid_duplicate_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events").allow_duplicate_keys().without_rowid();
+
+        assert!(table.create(&conn).is_err());
+    }
+
+    #[test]
+    fn test_get_results() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("visits", &3).unwrap();
+        db.set("name", "jimmy").unwrap();
+
+        let results = db.get_results::<i64>(&["visits", "missing", "name"]);
+
+        assert_eq!(3, results.len());
+        assert_eq!("visits", results[0].0);
+        assert_eq!(Some(3), *results[0].1.as_ref().unwrap());
+        assert_eq!("missing", results[1].0);
+        assert_eq!(None, *results[1].1.as_ref().unwrap());
+        assert_eq!("name", results[2].0);
+        assert!(matches!(results[2].1, Err(Error::InvalidColumnType(..))));
+    }
+
+    #[test]
+    fn test_get_from_reader() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[("jimmy", 1), ("bob", 2)]).unwrap();
+
+        let keys = "bob\r\nmissing\n\njimmy";
+        let values = db.get_from_reader::<_, i64>(keys.as_bytes()).unwrap();
+
+        assert_eq!(
+            vec![
+                ("bob".to_owned(), Some(2)),
+                ("missing".to_owned(), None),
+                ("jimmy".to_owned(), Some(1)),
+            ],
+            values
+        );
+        assert!(db.get_from_reader::<_, i64>(&[0xff, b'\n'][..]).is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[("jimmy", 10), ("bob", 3), ("sally", 7)])
+            .unwrap();
+
+        let entries: Vec<(String, i64)> = db
+            .query::<i64, _>("v >= ?1", params![5])
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert_eq!(
+            vec![("jimmy".to_owned(), 10), ("sally".to_owned(), 7)],
+            entries
+        );
+        assert!(db.query::<i64, _>("not valid sql", []).is_err());
+    }
+
+    #[test]
+    fn test_copy_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users").without_rowid();
+        let db = table.create(&conn).unwrap();
+        conn.execute("ALTER TABLE users ADD COLUMN tenant TEXT", ())
+            .unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let copy = db.copy_schema("users_2").unwrap();
+
+        assert_eq!(
+            table_columns("users", &conn).unwrap(),
+            table_columns("users_2", &conn).unwrap()
+        );
+        assert!(copy.describe().unwrap().ends_with("WITHOUT ROWID"));
+        assert!(copy.keys().unwrap().is_empty());
+
+        copy.set("bob", "def").unwrap();
+        assert_eq!("def", copy.get::<String>("bob").unwrap());
+        assert_eq!(vec!["jimmy"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_copy_schema_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        Table::new("posts").create(&conn).unwrap();
+
+        assert!(db.copy_schema("posts").is_err());
+        assert!(db.copy_schema("bad name").is_err());
+        assert!(Table::existing("missing", &conn)
+            .copy_schema("copy")
+            .is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("events");
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[
+            ("2024-01-31", 1),
+            ("2024-02-01", 2),
+            ("2024-02-14", 3),
+            ("2024-02-29", 4),
+            ("2024-03-01", 5),
+        ])
+        .unwrap();
+
+        let keys = db
+            .range::<i32>("2024-02-01", "2024-03-01")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect::<Vec<String>>();
+
+        assert_eq!(vec!["2024-02-01", "2024-02-14", "2024-02-29"], keys);
+        assert!(db
+            .range::<i32>("2024-03-01", "2024-03-01")
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .range::<i32>("2024-03-01", "2024-01-01")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_with_retry_busy() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let calls = std::cell::Cell::new(0);
+
+        let result = db.with_retry(5, Duration::from_millis(1), || {
+            calls.set(calls.get() + 1);
+
+            if calls.get() < 3 {
+                Err(Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_BUSY),
+                    None,
+                ))
+            } else {
+                db.set("jimmy", "abc")
+            }
+        });
 
-        assert_eq!(true, result.is_ok());
         assert_eq!(1, result.unwrap());
+        assert_eq!(3, calls.get());
+    }
 
-        let jimmy = db.get::<String>("jimmy").unwrap();
+    #[test]
+    fn test_with_retry_gives_up() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let calls = std::cell::Cell::new(0);
 
-        assert_eq!(jimmy, "456");
+        let busy = db.with_retry(3, Duration::from_millis(1), || {
+            calls.set(calls.get() + 1);
+            Err::<(), _>(Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_LOCKED),
+                None,
+            ))
+        });
+        assert!(busy.is_err());
+        assert_eq!(3, calls.get());
+
+        // Other errors aren't retried
+        calls.set(0);
+        let missing = db.with_retry(3, Duration::from_millis(1), || {
+            calls.set(calls.get() + 1);
+            db.get::<String>("missing")
+        });
+        assert!(matches!(missing, Err(Error::QueryReturnedNoRows)));
+        assert_eq!(1, calls.get());
     }
 
     #[test]
-    fn test_updating_missing_value() {
+    fn test_contains_key() {
         let conn = Connection::open_in_memory().unwrap();
         let table = Table::new("users");
         let db = table.create(&conn).unwrap();
 
-        let result = db.update("jimmy", "456");
+        assert!(!db.contains_key("jimmy").unwrap());
 
-        assert_eq!(true, result.is_ok());
-        assert_eq!(0, result.unwrap());
+        db.set("jimmy", &rusqlite::types::Null).unwrap();
 
-        let jimmy = db.get::<String>("jimmy");
+        assert!(db.contains_key("jimmy").unwrap());
+        assert!(!db.contains_key("JIMMY").unwrap());
+
+        db.remove("jimmy").unwrap();
+
+        assert!(!db.contains_key("jimmy").unwrap());
+        assert!(Table::existing("missing", &conn)
+            .contains_key("jimmy")
+            .is_err());
+    }
+
+    #[test]
+    fn test_exists() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert!(!db.exists("jimmy").unwrap());
+
+        db.set("jimmy", &rusqlite::types::Null).unwrap();
+
+        assert!(db.exists("jimmy").unwrap());
+        assert!(!db.exists("JIMMY").unwrap());
+
+        db.remove("jimmy").unwrap();
 
-        assert_eq!(true, jimmy.is_err());
+        assert!(!db.exists("jimmy").unwrap());
+        assert!(Table::existing("missing", &conn).exists("jimmy").is_err());
     }
 }
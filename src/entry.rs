@@ -0,0 +1,209 @@
+use crate::{transaction::Scope, DatabaseTable};
+use rusqlite::{types::FromSql, Error, OptionalExtension, Result, ToSql, TransactionBehavior};
+
+/// A view into a single key for read-modify-write, like std's `HashMap` entry
+///
+/// Returned by `DatabaseTable::entry`, which reads the current value in an
+/// immediate transaction so nothing else can write the key until the entry
+/// is finished. `and_modify` changes the value in memory, `or_insert`,
+/// `or_insert_with` & `save` write it back and commit. Dropping the entry
+/// without finishing it rolls back, discarding any change.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::Table;
+/// use rusqlite::Connection;
+///
+/// let connection = Connection::open_in_memory()?;
+/// # Table::new("counters").create(&connection)?;
+/// let table = Table::existing("counters", &connection);
+///
+/// for _ in 0..3 {
+///     table.entry::<i64>("visits")?.and_modify(|v| *v += 1).or_insert(1)?;
+/// }
+///
+/// assert_eq!(3, table.get::<i64>("visits")?);
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub struct Entry<'a, T> {
+    table: DatabaseTable<'a>,
+    transaction: Scope<'a>,
+    key: String,
+    value: Option<T>,
+    modified: bool,
+}
+
+impl<'a, T: FromSql + ToSql> Entry<'a, T> {
+    /// Returns the key of the entry
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the current value, `None` if the key is missing
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Modifies the value in place if the key exists
+    ///
+    /// The change is written by `or_insert`, `or_insert_with` or `save`.
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Some(value) = &mut self.value {
+            f(value);
+            self.modified = true;
+        }
+
+        self
+    }
+
+    /// Inserts `default` if the key is missing and commits
+    ///
+    /// Returns the value now stored under the key.
+    pub fn or_insert(self, default: T) -> Result<T, Error> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `f` if the key is missing and commits
+    ///
+    /// `f` is only called when the key is missing. Returns the value now
+    /// stored under the key.
+    pub fn or_insert_with<F: FnOnce() -> T>(mut self, f: F) -> Result<T, Error> {
+        let value = match self.value.take() {
+            Some(value) if self.modified => {
+                self.table.update(&self.key, &value)?;
+                value
+            }
+            Some(value) => value,
+            None => {
+                let value = f();
+                self.table.set(&self.key, &value)?;
+                value
+            }
+        };
+
+        self.transaction.commit()?;
+
+        Ok(value)
+    }
+
+    /// Writes any `and_modify` change without inserting a missing key
+    ///
+    /// Returns the current value, `None` if the key is missing.
+    pub fn save(self) -> Result<Option<T>, Error> {
+        if let (Some(value), true) = (&self.value, self.modified) {
+            self.table.update(&self.key, value)?;
+        }
+
+        self.transaction.commit()?;
+
+        Ok(self.value)
+    }
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Gets the entry for a key for in-place read-modify-write
+    ///
+    /// Starts an immediate transaction and reads the key's current value,
+    /// see `Entry` for how it's written back. Within an open transaction,
+    /// e.g. `begin`, a savepoint is used instead.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let name = table.entry::<String>("jimmy")?.or_insert("abc".to_owned())?;
+    /// assert_eq!("abc", name);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn entry<T: FromSql + ToSql>(&self, key: &str) -> Result<Entry<'a, T>, Error> {
+        let transaction = Scope::begin_with(self.connection, TransactionBehavior::Immediate)?;
+        let value = self.read::<T>(key).optional()?;
+
+        Ok(Entry {
+            table: self.clone(),
+            transaction,
+            key: key.to_owned(),
+            value,
+            modified: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::{Connection, OptionalExtension};
+
+    #[test]
+    fn test_or_insert_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("counters");
+        let db = table.create(&conn).unwrap();
+
+        let visits = db
+            .entry::<i64>("visits")
+            .unwrap()
+            .and_modify(|v| *v += 1)
+            .or_insert(1)
+            .unwrap();
+
+        assert_eq!(1, visits);
+        assert_eq!(1, db.get::<i64>("visits").unwrap());
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_and_modify_existing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("counters");
+        let db = table.create(&conn).unwrap();
+        db.set("visits", &41).unwrap();
+
+        let visits = db
+            .entry::<i64>("visits")
+            .unwrap()
+            .and_modify(|v| *v += 1)
+            .or_insert_with(|| panic!("the key exists"))
+            .unwrap();
+
+        assert_eq!(42, visits);
+        assert_eq!(42, db.get::<i64>("visits").unwrap());
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_save_and_drop() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let entry = db.entry::<String>("jimmy").unwrap();
+        assert_eq!("jimmy", entry.key());
+        assert_eq!(Some(&"abc".to_owned()), entry.get());
+
+        // Dropping an unfinished entry discards the change
+        drop(entry.and_modify(|v| v.push('d')));
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+
+        let saved = db
+            .entry::<String>("jimmy")
+            .unwrap()
+            .and_modify(|v| v.push('d'))
+            .save()
+            .unwrap();
+        assert_eq!(Some("abcd".to_owned()), saved);
+        assert_eq!("abcd", db.get::<String>("jimmy").unwrap());
+
+        assert_eq!(None, db.entry::<String>("bob").unwrap().save().unwrap());
+        assert_eq!(None, db.get::<String>("bob").optional().unwrap());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{table_columns, transaction::Scope, DatabaseTable, Table, KEY_COLUMN, NOW};
+use rusqlite::{params, Error, ErrorCode, Result};
+
+/// Column counting how many times `get` has read a row
+pub(crate) const ACCESS_COUNT_COLUMN: &str = "access_count";
+/// Column holding when `get` last read a row
+pub(crate) const ACCESSED_AT_COLUMN: &str = "accessed_at";
+
+impl<'a> Table<'a> {
+    /// Tracks how often and how recently each row is read
+    ///
+    /// Adds `access_count` & `accessed_at` columns which `get` bumps on
+    /// every read, letting `evict_to` cap the table as an on-disk cache.
+    /// Any handle to a table with the columns records reads, including
+    /// one from `existing`, which checks the schema on its first `get`.
+    /// Recording a read is a write, it isn't reported to `watch` but is
+    /// skipped on read-only and `query_only` connections. Reads made by
+    /// methods that write the value back, e.g. `set_if_changed`,
+    /// `map_value` or `entry`, aren't recorded.
+    /// The columns are only added when the table is first created, a
+    /// table created without this option lacks them.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("cache").track_access();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set("page", "<html>")?;
+    /// table.get::<String>("page")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn track_access(mut self) -> Self {
+        self.track_access = true;
+        self
+    }
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Whether the table has the access columns `get` bumps
+    ///
+    /// Read from the schema once per handle, unless `create` already knows.
+    pub(crate) fn tracks_access(&self) -> Result<bool, Error> {
+        if let Some(tracked) = self.track_access.get() {
+            return Ok(tracked);
+        }
+
+        let tracked = table_columns(self.name, self.connection)?
+            .iter()
+            .any(|column| column == ACCESS_COUNT_COLUMN);
+        self.track_access.set(Some(tracked));
+
+        Ok(tracked)
+    }
+
+    /// Bumps the access metadata of a row after `get` reads it
+    ///
+    /// Skipped on read-only and `query_only` connections, which can't
+    /// write the metadata.
+    pub(crate) fn record_access(&self, key: &str) -> Result<(), Error> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "UPDATE {} SET {} = {} + 1, {} = {} WHERE {} = ?1",
+            self.name,
+            ACCESS_COUNT_COLUMN,
+            ACCESS_COUNT_COLUMN,
+            ACCESSED_AT_COLUMN,
+            NOW,
+            KEY_COLUMN
+        ))?;
+        match statement.execute(params![key]) {
+            Err(err) if err.sqlite_error_code() == Some(ErrorCode::ReadOnly) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Evicts the least used rows until at most `max_entries` remain
+    ///
+    /// Rows read the fewest times are removed first, ties are broken by
+    /// the least recently read and then the oldest inserted. Counts come
+    /// from `get` on a table created with `track_access`, tables without
+    /// the columns fail with an error, as do `WITHOUT ROWID` tables.
+    /// Runs within one transaction and returns the number of removed rows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("cache").track_access();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set_many(&[("a", 1), ("b", 2), ("c", 3)])?;
+    /// table.get::<i64>("a")?;
+    ///
+    /// assert_eq!(1, table.evict_to(2)?);
+    /// assert_eq!(vec!["a", "c"], table.keys()?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn evict_to(&self, max_entries: usize) -> Result<usize, Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let count = self.count()?;
+        if count <= max_entries {
+            return Ok(0);
+        }
+
+        let removed = self.connection.execute(
+            &format!(
+                "DELETE FROM {} WHERE rowid IN (
+                    SELECT rowid FROM {} ORDER BY {}, {}, rowid LIMIT ?1
+                )",
+                self.name, self.name, ACCESS_COUNT_COLUMN, ACCESSED_AT_COLUMN
+            ),
+            params![count - max_entries],
+        )?;
+
+        transaction.commit()?;
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_get_records_access() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache").track_access();
+        let db = table.create(&conn).unwrap();
+
+        db.set("page", "<html>").unwrap();
+        conn.execute("UPDATE cache SET accessed_at = 0", ())
+            .unwrap();
+
+        db.get::<String>("page").unwrap();
+        db.get::<String>("page").unwrap();
+        Table::existing("cache", &conn)
+            .get::<String>("page")
+            .unwrap();
+        Table::new("cache")
+            .create(&conn)
+            .unwrap()
+            .get::<String>("page")
+            .unwrap();
+
+        let (count, accessed_at): (i64, i64) = conn
+            .query_row("SELECT access_count, accessed_at FROM cache", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+
+        assert_eq!(4, count);
+        assert!(accessed_at > 0);
+        assert!(db.get::<String>("missing").is_err());
+    }
+
+    #[test]
+    fn test_internal_reads_not_recorded() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache").track_access();
+        let db = table.create(&conn).unwrap();
+        db.set("visits", &1).unwrap();
+
+        assert!(!db.set_if_changed("visits", &1).unwrap());
+        db.map_value("visits", |visits: i64| visits + 1).unwrap();
+        db.entry::<i64>("visits").unwrap().save().unwrap();
+
+        let count: i64 = conn
+            .query_row("SELECT access_count FROM cache", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, count);
+
+        conn.pragma_update(None, "query_only", true).unwrap();
+        assert_eq!(2, db.get::<i64>("visits").unwrap());
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_access_bookkeeping_is_quiet() {
+        use crate::Operation;
+        use std::sync::{Arc, Mutex};
+
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache").track_access();
+        let db = table.create(&conn).unwrap();
+        db.set("visits", &1).unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        db.watch(move |operation, key| recorded.lock().unwrap().push((operation, key.to_owned())))
+            .unwrap();
+
+        db.get::<i64>("visits").unwrap();
+        db.update("visits", &2).unwrap();
+
+        assert_eq!(
+            vec![(Operation::Update, "visits".to_owned())],
+            *changes.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_evict_to_removes_oldest() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache").track_access();
+        let db = table.create(&conn).unwrap();
+
+        for i in 0..10 {
+            db.set(&format!("key{}", i), &i).unwrap();
+        }
+
+        assert_eq!(0, db.evict_to(10).unwrap());
+        assert_eq!(7, db.evict_to(3).unwrap());
+        assert_eq!(vec!["key7", "key8", "key9"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_evict_to_keeps_used_entries() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache").track_access();
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)])
+            .unwrap();
+        db.get::<i64>("a").unwrap();
+        db.get::<i64>("a").unwrap();
+        db.get::<i64>("b").unwrap();
+
+        assert_eq!(2, db.evict_to(2).unwrap());
+        assert_eq!(vec!["a", "b"], db.keys().unwrap());
+
+        assert_eq!(1, db.evict_to(1).unwrap());
+        assert_eq!(vec!["a"], db.keys().unwrap());
+        assert_eq!(1, db.evict_to(0).unwrap());
+    }
+
+    #[test]
+    fn test_evict_to_through_existing_handle() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("cache").track_access().create(&conn).unwrap();
+        let db = Table::existing("cache", &conn);
+
+        db.set_many(&[("a", 1), ("b", 2), ("c", 3)]).unwrap();
+        db.get::<i64>("a").unwrap();
+
+        assert_eq!(2, db.evict_to(1).unwrap());
+        assert_eq!(vec!["a"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_evict_to_untracked_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("cache");
+        let db = table.create(&conn).unwrap();
+
+        db.set_many(&[("a", 1), ("b", 2)]).unwrap();
+
+        assert!(db.evict_to(1).is_err());
+        assert_eq!(0, db.evict_to(2).unwrap());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{DatabaseTable, Table, KEY_COLUMN, NOW, VALUE_COLUMN};
+use rusqlite::{params, Error, OptionalExtension, Result, ToSql};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Column holding when a row expires, `NULL` for rows that never expire
+pub(crate) const EXPIRES_AT_COLUMN: &str = "expires_at";
+
+/// Returns the time elapsed since the unix epoch
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+}
+
+impl<'a> Table<'a> {
+    /// Adds an `expires_at` column so keys can be given a time to live
+    ///
+    /// Enables `set_expiring`, `ttl` & `purge_expired`. Keys stored with
+    /// `set` never expire. Expired keys aren't hidden from reads, they're
+    /// kept until `purge_expired` removes them. Always open the table
+    /// through a builder with this option, a table created without it
+    /// lacks the column.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use std::time::Duration;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("sessions").expiring();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set_expiring("jimmy", "token", Duration::from_secs(60))?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn expiring(mut self) -> Self {
+        self.expiring = true;
+        self
+    }
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Inserts a key & value that expires after `ttl`
+    ///
+    /// The expiry is stored with second precision, rounded up. A `ttl` too
+    /// large to store, such as the `Duration::MAX` that `ttl` reports for
+    /// keys that never expire, is clamped to the latest storable expiry.
+    /// Requires a table created with `expiring`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use std::time::Duration;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("sessions").expiring();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set_expiring("jimmy", "token", Duration::from_secs(60))?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value), fields(table = self.name)))]
+    pub fn set_expiring<T: ToSql + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<usize, Error> {
+        let expires_at = now()
+            .checked_add(ttl)
+            .and_then(|expires_at| {
+                let seconds = expires_at.as_secs() + (expires_at.subsec_nanos() > 0) as u64;
+                i64::try_from(seconds).ok()
+            })
+            .unwrap_or(i64::MAX);
+
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} ({}, {}, {}) VALUES(?1, ?2, ?3)",
+                self.name, KEY_COLUMN, VALUE_COLUMN, EXPIRES_AT_COLUMN
+            ),
+            params![key, value, expires_at],
+        )
+    }
+
+    /// Returns how long a key has left to live
+    ///
+    /// `None` if the key is missing, `Duration::MAX` if it never expires
+    /// and `Duration::ZERO` once it has expired, helping clients decide
+    /// whether to refresh a value. Requires a table created with
+    /// `expiring`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use std::time::Duration;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("sessions").expiring();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set_expiring("jimmy", "token", Duration::from_secs(60))?;
+    /// table.set("bob", "token")?;
+    ///
+    /// assert!(table.ttl("jimmy")?.unwrap() > Duration::ZERO);
+    /// assert_eq!(Some(Duration::MAX), table.ttl("bob")?);
+    /// assert_eq!(None, table.ttl("sally")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn ttl(&self, key: &str) -> Result<Option<Duration>, Error> {
+        let expires_at = self
+            .connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
+                    EXPIRES_AT_COLUMN, self.name, KEY_COLUMN
+                ),
+                params![key],
+                |row| row.get::<usize, Option<i64>>(0),
+            )
+            .optional()?;
+
+        Ok(expires_at.map(|expires_at| match expires_at {
+            Some(expires_at) => Duration::from_secs(expires_at.max(0) as u64).saturating_sub(now()),
+            None => Duration::MAX,
+        }))
+    }
+
+    /// Removes every expired key
+    ///
+    /// Returns the number of removed rows. Requires a table created with
+    /// `expiring`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn purge_expired(&self) -> Result<usize, Error> {
+        self.connection.execute(
+            &format!(
+                "DELETE FROM {} WHERE {} <= {}",
+                self.name, EXPIRES_AT_COLUMN, NOW
+            ),
+            (),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_ttl_with_expiry() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("sessions").expiring();
+        let db = table.create(&conn).unwrap();
+
+        db.set_expiring("jimmy", "token", Duration::from_secs(60))
+            .unwrap();
+
+        let ttl = db.ttl("jimmy").unwrap().unwrap();
+        assert!(ttl > Duration::from_secs(55));
+        assert!(ttl <= Duration::from_secs(61));
+        assert_eq!("token", db.get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_huge_ttl_is_clamped() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("sessions").expiring();
+        let db = table.create(&conn).unwrap();
+
+        db.set_expiring("jimmy", "token", Duration::MAX).unwrap();
+        db.set_expiring("bob", "token", Duration::from_secs(u64::MAX / 2))
+            .unwrap();
+
+        let year = Duration::from_secs(365 * 24 * 60 * 60);
+        assert!(db.ttl("jimmy").unwrap().unwrap() > year * 1000);
+        assert!(db.ttl("bob").unwrap().unwrap() > year * 1000);
+        assert_eq!(0, db.purge_expired().unwrap());
+    }
+
+    #[test]
+    fn test_ttl_without_expiry() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("sessions").expiring();
+        let db = table.create(&conn).unwrap();
+
+        db.set("bob", "token").unwrap();
+
+        assert_eq!(Some(Duration::MAX), db.ttl("bob").unwrap());
+    }
+
+    #[test]
+    fn test_ttl_missing_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("sessions").expiring();
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(None, db.ttl("sally").unwrap());
+    }
+
+    #[test]
+    fn test_expired_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("sessions").expiring();
+        let db = table.create(&conn).unwrap();
+
+        db.set_expiring("jimmy", "token", Duration::from_secs(60))
+            .unwrap();
+        db.set_expiring("bob", "token", Duration::from_secs(60))
+            .unwrap();
+        db.set("sally", "token").unwrap();
+        conn.execute("UPDATE sessions SET expires_at = 1 WHERE k = 'jimmy'", ())
+            .unwrap();
+
+        assert_eq!(Some(Duration::ZERO), db.ttl("jimmy").unwrap());
+        assert_eq!(1, db.purge_expired().unwrap());
+        assert_eq!(vec!["bob", "sally"], db.keys().unwrap());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::DatabaseTable;
+use rusqlite::{types::FromSql, Error, OptionalExtension, Result, ToSql};
+use std::future::Future;
+
+impl<'a> DatabaseTable<'a> {
+    /// Get a value by key, fetching and storing it asynchronously on a miss
+    ///
+    /// The async counterpart to `get_or_else` for cache-aside reads: when
+    /// the key is missing `loader` is called and its future awaited, then
+    /// the fetched value is stored with `set` before being returned. The
+    /// loader isn't called when the key exists. SQLite is still queried
+    /// synchronously and the returned future borrows the connection, so it
+    /// isn't `Send`, await it on the task owning the connection. Requires
+    /// the `async` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// async fn fetch_email() -> String {
+    ///     "abc@abc.com".to_owned()
+    /// }
+    ///
+    /// # async fn run() -> adrodb::Result<()> {
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("emails").create(&connection)?;
+    /// let table = Table::existing("emails", &connection);
+    ///
+    /// let email = table.get_or_fetch("jimmy", fetch_email).await?;
+    /// assert_eq!("abc@abc.com", email);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, loader), fields(table = self.name)))]
+    pub async fn get_or_fetch<T, F, Fut>(&self, key: &str, loader: F) -> Result<T, Error>
+    where
+        T: FromSql + ToSql,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        if let Some(value) = self.get::<T>(key).optional()? {
+            return Ok(value);
+        }
+
+        let value = loader().await;
+        self.set(key, &value)?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::Connection;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn test_get_or_fetch_only_loads_on_miss() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("emails");
+        let db = table.create(&conn).unwrap();
+        let loads = Cell::new(0);
+
+        let loader = || async {
+            loads.set(loads.get() + 1);
+            "abc@abc.com".to_owned()
+        };
+
+        let email = db.get_or_fetch("jimmy", loader).await.unwrap();
+        assert_eq!("abc@abc.com", email);
+        assert_eq!(1, loads.get());
+
+        let email = db.get_or_fetch("jimmy", loader).await.unwrap();
+        assert_eq!("abc@abc.com", email);
+        assert_eq!(1, loads.get());
+
+        assert_eq!("abc@abc.com", db.get::<String>("jimmy").unwrap());
+    }
+}
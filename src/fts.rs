@@ -0,0 +1,344 @@
+#[cfg(feature = "compression")]
+use crate::table_columns;
+use crate::{table_type, DatabaseTable, KeyValue, Table, KEY_COLUMN, VALUE_COLUMN};
+#[cfg(feature = "compression")]
+use rusqlite::ffi;
+use rusqlite::{params, Connection, Error, Result};
+
+impl<'a> Table<'a> {
+    /// Indexes values for full-text search with FTS5
+    ///
+    /// `create` also builds a contentless FTS5 table named `{name}_fts`
+    /// mirroring the value column, kept in sync by triggers on insert,
+    /// update & delete, enabling `search`. Values are indexed by their
+    /// text form, rows already in the table are indexed when the FTS5
+    /// table is first created. Can't be combined with `without_rowid` as
+    /// the index is linked to rows by their rowid, nor with
+    /// `compress_above` as the index would hold compressed bytes, `create`
+    /// fails for a table with a `compressed` column. Requires the `fts`
+    /// feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("docs").full_text_search();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set("intro", "SQLite is a small, fast database engine")?;
+    ///
+    /// assert_eq!("intro", table.search("fast")?[0].key);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn full_text_search(mut self) -> Self {
+        self.full_text = true;
+        self
+    }
+}
+
+/// Sync triggers created by `create_index`
+const TRIGGERS: [&str; 3] = ["insert", "delete", "update"];
+
+/// Name of the FTS5 table indexing a table
+fn index_name(name: &str) -> String {
+    format!("{}_fts", name)
+}
+
+/// Whether a table has an FTS5 index
+fn has_index(name: &str, connection: &Connection) -> Result<bool, Error> {
+    Ok(table_type(&index_name(name), connection)?.as_deref() == Some("virtual"))
+}
+
+/// Creates the FTS5 table and sync triggers for a table
+///
+/// Run by `create` for tables built with `full_text_search`.
+pub(crate) fn create_index(name: &str, connection: &Connection) -> Result<(), Error> {
+    // The triggers would index the gzip bytes rather than the text
+    #[cfg(feature = "compression")]
+    if table_columns(name, connection)?
+        .iter()
+        .any(|column| column == crate::compress::COMPRESSED_COLUMN)
+    {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!(
+                "full_text_search can't index the compressed table {}",
+                name
+            )),
+        ));
+    }
+
+    let fts = index_name(name);
+
+    let backfill = if has_index(name, connection)? {
+        String::new()
+    } else {
+        format!(
+            "INSERT INTO {} (rowid, {}) SELECT rowid, {} FROM {};",
+            fts, VALUE_COLUMN, VALUE_COLUMN, name
+        )
+    };
+
+    connection.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS {fts} USING fts5({v}, content='');
+        {backfill}
+        CREATE TRIGGER IF NOT EXISTS {fts}_insert AFTER INSERT ON {name} BEGIN
+            INSERT INTO {fts} (rowid, {v}) VALUES (new.rowid, new.{v});
+        END;
+        CREATE TRIGGER IF NOT EXISTS {fts}_delete AFTER DELETE ON {name} BEGIN
+            INSERT INTO {fts} ({fts}, rowid, {v}) VALUES ('delete', old.rowid, old.{v});
+        END;
+        CREATE TRIGGER IF NOT EXISTS {fts}_update AFTER UPDATE OF {v} ON {name} BEGIN
+            INSERT INTO {fts} ({fts}, rowid, {v}) VALUES ('delete', old.rowid, old.{v});
+            INSERT INTO {fts} (rowid, {v}) VALUES (new.rowid, new.{v});
+        END;",
+        fts = fts,
+        name = name,
+        v = VALUE_COLUMN,
+        backfill = backfill
+    ))
+}
+
+/// Drops the FTS5 table of a dropped table, if it had one
+///
+/// Dropping the table already dropped the sync triggers.
+pub(crate) fn drop_index(name: &str, connection: &Connection) -> Result<(), Error> {
+    if has_index(name, connection)? {
+        connection.execute_batch(&format!("DROP TABLE {}", index_name(name)))?;
+    }
+
+    Ok(())
+}
+
+/// Renames the FTS5 table & sync triggers of a renamed table
+///
+/// `ALTER TABLE` already points the triggers at the renamed tables, they
+/// are recreated so their names follow too.
+pub(crate) fn rename_index(
+    name: &str,
+    new_name: &str,
+    connection: &Connection,
+) -> Result<(), Error> {
+    if !has_index(name, connection)? {
+        return Ok(());
+    }
+
+    connection.execute_batch(&format!(
+        "ALTER TABLE {} RENAME TO {}",
+        index_name(name),
+        index_name(new_name)
+    ))?;
+    for trigger in TRIGGERS {
+        connection.execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS {}_{}",
+            index_name(name),
+            trigger
+        ))?;
+    }
+
+    create_index(new_name, connection)
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Get every entry whose value matches a full-text query
+    ///
+    /// Runs an FTS5 `MATCH`, so `query` uses the
+    /// [FTS5 query syntax](https://www.sqlite.org/fts5.html#full_text_query_syntax),
+    /// e.g. `sqlite AND fast` or `"exact phrase"`. Entries are ordered by
+    /// relevance, best match first. Requires a table created with
+    /// `full_text_search` and the `fts` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let table = Table::new("docs").full_text_search();
+    /// let table = table.create(&connection)?;
+    ///
+    /// table.set("intro", "SQLite is a small, fast database engine")?;
+    /// table.set("rust", "Rust is a fast systems language")?;
+    ///
+    /// let found = table.search("fast AND database")?;
+    /// assert_eq!(1, found.len());
+    /// assert_eq!("intro", found[0].key);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn search(&self, query: &str) -> Result<Vec<KeyValue<String>>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT t.{k}, t.{v} FROM {name} AS t
+            JOIN (SELECT rowid, rank FROM {name}_fts WHERE {name}_fts MATCH ?1) AS matches
+            ON t.rowid = matches.rowid
+            ORDER BY matches.rank",
+            k = KEY_COLUMN,
+            v = VALUE_COLUMN,
+            name = self.name
+        ))?;
+        let entries = statement
+            .query_map(params![query], KeyValue::from_row)?
+            .collect::<Result<Vec<KeyValue<String>>, Error>>()?;
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(db: &DatabaseTable, query: &str) -> Vec<String> {
+        let mut keys = db
+            .search(query)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_search() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+
+        db.set("sqlite", "SQLite is a small, fast database engine")
+            .unwrap();
+        db.set("rust", "Rust is a fast systems language").unwrap();
+        db.set("cooking", "Slow roasted vegetables").unwrap();
+
+        assert_eq!(vec!["rust", "sqlite"], keys(&db, "fast"));
+        assert_eq!(vec!["sqlite"], keys(&db, "fast AND database"));
+        assert_eq!(vec!["cooking"], keys(&db, "\"roasted vegetables\""));
+        assert!(keys(&db, "python").is_empty());
+
+        let found = db.search("engine").unwrap();
+        assert_eq!("SQLite is a small, fast database engine", found[0].value);
+    }
+
+    #[test]
+    fn test_search_stays_in_sync() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+
+        db.set("a", "apples and pears").unwrap();
+        db.set("b", "bananas").unwrap();
+
+        db.update("a", "oranges").unwrap();
+        assert!(keys(&db, "apples").is_empty());
+        assert_eq!(vec!["a"], keys(&db, "oranges"));
+
+        db.remove("b").unwrap();
+        assert!(keys(&db, "bananas").is_empty());
+
+        // Reopening doesn't index rows twice
+        let db = table.create(&conn).unwrap();
+        assert_eq!(1, db.search("oranges").unwrap().len());
+    }
+
+    #[test]
+    fn test_search_indexes_existing_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        let db = Table::new("docs").create(&conn).unwrap();
+        db.set("a", "written before indexing").unwrap();
+
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(vec!["a"], keys(&db, "indexing"));
+    }
+
+    #[test]
+    fn test_search_without_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        let db = Table::new("docs").create(&conn).unwrap();
+
+        assert!(db.search("anything").is_err());
+    }
+
+    #[test]
+    fn test_drop_all_drops_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+        db.set("a", "apples").unwrap();
+
+        assert_eq!(1, Table::drop_all(&conn).unwrap());
+        assert!(!has_index("docs", &conn).unwrap());
+
+        let db = table.create(&conn).unwrap();
+        db.set("b", "bananas").unwrap();
+        assert!(keys(&db, "apples").is_empty());
+        assert_eq!(vec!["b"], keys(&db, "bananas"));
+    }
+
+    #[test]
+    fn test_rename_table_renames_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("notes").full_text_search();
+        let db = table.create(&conn).unwrap();
+        db.set("a", "apples").unwrap();
+
+        let db = db.rename_table("memos").unwrap();
+        assert!(!has_index("notes", &conn).unwrap());
+        assert_eq!(vec!["a"], keys(&db, "apples"));
+
+        db.set("b", "bananas").unwrap();
+        db.update("a", "cherries").unwrap();
+        assert_eq!(vec!["b"], keys(&db, "bananas"));
+        assert!(keys(&db, "apples").is_empty());
+
+        // Reopening keeps a single set of triggers
+        let table = Table::new("memos").full_text_search();
+        let db = table.create(&conn).unwrap();
+        db.set("c", "cherries").unwrap();
+        assert_eq!(vec!["a", "c"], keys(&db, "cherries"));
+        let triggers: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(3, triggers);
+    }
+
+    #[test]
+    fn test_clear_all_clears_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").full_text_search();
+        let db = table.create(&conn).unwrap();
+        db.set("a", "apples").unwrap();
+
+        assert_eq!(
+            1,
+            Table::clear_all(&conn, &["docs", "docs_fts", "docs_fts_data"]).unwrap()
+        );
+        assert!(keys(&db, "apples").is_empty());
+
+        db.set("a", "bananas").unwrap();
+        assert_eq!(vec!["a"], keys(&db, "bananas"));
+        assert!(keys(&db, "apples").is_empty());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_search_rejects_compression() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("docs").compress_above(64).full_text_search();
+
+        assert!(table.create(&conn).is_err());
+        assert!(!has_index("docs", &conn).unwrap());
+
+        let table = Table::new("docs").full_text_search();
+        assert!(table.create(&conn).is_err());
+    }
+}
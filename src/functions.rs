@@ -0,0 +1,68 @@
+use crate::DatabaseTable;
+use rusqlite::{
+    functions::{Context, FunctionFlags},
+    Error, Result, ToSql,
+};
+use std::panic::UnwindSafe;
+
+impl<'a> DatabaseTable<'a> {
+    /// Registers a custom scalar SQL function on the connection
+    ///
+    /// Wraps `Connection::create_scalar_function` so the function can be
+    /// used in `query` conditions. `n_arg` is the number of arguments the
+    /// function takes, or `-1` for any number. The function is registered
+    /// on the whole connection, not only this table. Requires the
+    /// `functions` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::{params, Connection};
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("scores").create(&connection)?;
+    /// let table = Table::existing("scores", &connection);
+    ///
+    /// table.register_function("double", 1, |ctx| ctx.get::<i64>(0).map(|x| x * 2))?;
+    ///
+    /// table.set("jimmy", &10)?;
+    /// let entries = table.query::<i64, _>("double(v) > ?1", params![15])?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn register_function<F, T>(&self, name: &str, n_arg: i32, f: F) -> Result<(), Error>
+    where
+        F: FnMut(&Context<'_>) -> Result<T> + Send + UnwindSafe + 'static,
+        T: ToSql,
+    {
+        self.connection
+            .create_scalar_function(name, n_arg, FunctionFlags::SQLITE_UTF8, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::{params, Connection};
+
+    #[test]
+    fn test_register_function() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("scores");
+        let db = table.create(&conn).unwrap();
+
+        db.register_function("double", 1, |ctx| ctx.get::<i64>(0).map(|x| x * 2))
+            .unwrap();
+        db.set_many(&[("jimmy", 10), ("bob", 3), ("sally", 8)])
+            .unwrap();
+
+        let keys = db
+            .query::<i64, _>("double(v) > ?1", params![15])
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect::<Vec<String>>();
+
+        assert_eq!(vec!["jimmy", "sally"], keys);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::DatabaseTable;
+use rusqlite::{types::FromSql, Error, Result, ToSql};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of the counters kept by an `InstrumentedTable`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// Number of `get` calls
+    pub gets: u64,
+    /// Number of `get` calls which found the key
+    pub hits: u64,
+    /// Number of `get` calls where the key was missing
+    pub misses: u64,
+    /// Number of `set` calls
+    pub sets: u64,
+    /// Number of `remove` calls
+    pub removes: u64,
+}
+
+/// A `DatabaseTable` which counts the operations made through it
+///
+/// A lightweight alternative to a metrics crate for quick profiling, e.g.
+/// in tests. Counters are plain atomics updated on every call, read them
+/// with `stats`. A `get` that fails for any reason other than a missing key
+/// counts towards `gets` but neither `hits` nor `misses`.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::{InstrumentedTable, Table};
+/// use rusqlite::Connection;
+///
+/// let connection = Connection::open_in_memory()?;
+/// let table = Table::new("users");
+/// let table = InstrumentedTable::new(table.create(&connection)?);
+///
+/// table.set("jimmy", "abc")?;
+/// table.get::<String>("jimmy")?;
+///
+/// assert_eq!(1, table.stats().hits);
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub struct InstrumentedTable<'a> {
+    table: DatabaseTable<'a>,
+    gets: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    sets: AtomicU64,
+    removes: AtomicU64,
+}
+
+impl<'a> InstrumentedTable<'a> {
+    /// Wraps a table with zeroed counters
+    pub fn new(table: DatabaseTable<'a>) -> Self {
+        InstrumentedTable {
+            table,
+            gets: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            sets: AtomicU64::new(0),
+            removes: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the wrapped table
+    ///
+    /// Operations made directly through the table aren't counted.
+    pub fn table(&self) -> &DatabaseTable<'a> {
+        &self.table
+    }
+
+    /// Returns the current value of every counter
+    pub fn stats(&self) -> TableStats {
+        TableStats {
+            gets: self.gets.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            sets: self.sets.load(Ordering::Relaxed),
+            removes: self.removes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get a value in the database by key, see `DatabaseTable::get`
+    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+
+        let result = self.table.get::<T>(key);
+        match &result {
+            Ok(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            Err(Error::QueryReturnedNoRows) => self.misses.fetch_add(1, Ordering::Relaxed),
+            Err(_) => 0,
+        };
+
+        result
+    }
+
+    /// Inserts some data into the table, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.sets.fetch_add(1, Ordering::Relaxed);
+        self.table.set(key, value)
+    }
+
+    /// Remove a value from the table, see `DatabaseTable::remove`
+    pub fn remove(&self, key: &str) -> Result<usize, Error> {
+        self.removes.fetch_add(1, Ordering::Relaxed);
+        self.table.remove(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_stats() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let table = InstrumentedTable::new(table.create(&conn).unwrap());
+
+        assert_eq!(TableStats::default(), table.stats());
+
+        table.set("jimmy", "abc").unwrap();
+        table.set("bob", "def").unwrap();
+        table.get::<String>("jimmy").unwrap();
+        table.get::<String>("bob").unwrap();
+        assert!(table.get::<String>("sally").is_err());
+        assert!(table.get::<i32>("jimmy").is_err());
+        table.remove("jimmy").unwrap();
+
+        assert_eq!(
+            TableStats {
+                gets: 4,
+                hits: 2,
+                misses: 1,
+                sets: 2,
+                removes: 1,
+            },
+            table.stats()
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    sanitize_identifier, Table, CREATED_AT_COLUMN, KEY_COLUMN, NOW, UPDATED_AT_COLUMN, VALUE_COLUMN,
+};
+use rusqlite::{params, types::FromSql, Connection, Error, Result, ToSql};
+
+/// A table keyed by integers, see `Table::with_integer_key`
+#[derive(Debug)]
+pub struct IntegerTable<'a> {
+    name: &'a str,
+}
+
+/// Operations on a table created by `IntegerTable::create`
+///
+/// Keys are `i64`s compared numerically, so `range` never suffers from
+/// text ordering quirks such as `"10"` sorting before `"9"`.
+#[derive(Debug)]
+pub struct IntegerDatabaseTable<'a> {
+    name: &'a str,
+    connection: &'a Connection,
+}
+
+impl<'a> Table<'a> {
+    /// Creates a table keyed by integers
+    ///
+    /// Suited to time series keyed by epoch millis or sequence numbers.
+    /// The key column is an `INTEGER PRIMARY KEY`, SQLite's rowid, so
+    /// lookups and `range` scans walk the table's own b-tree.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let readings = Table::with_integer_key("readings");
+    /// let readings = readings.create(&connection)?;
+    ///
+    /// readings.set(1_700_000_000_000, &21.5)?;
+    ///
+    /// assert_eq!(21.5, readings.get::<f64>(1_700_000_000_000)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn with_integer_key(name: &'a str) -> IntegerTable<'a> {
+        IntegerTable { name }
+    }
+}
+
+impl<'a> IntegerTable<'a> {
+    /// Creates the table in your database if it doesn't already exist
+    pub fn create(&self, connection: &'a Connection) -> Result<IntegerDatabaseTable<'a>, Error> {
+        sanitize_identifier(self.name)?;
+
+        connection.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                {} INTEGER PRIMARY KEY NOT NULL,
+                {},
+                {} INTEGER NOT NULL DEFAULT ({}),
+                {} INTEGER NOT NULL DEFAULT ({})
+            )",
+                self.name, KEY_COLUMN, VALUE_COLUMN, CREATED_AT_COLUMN, NOW, UPDATED_AT_COLUMN, NOW
+            ),
+            (),
+        )?;
+
+        Ok(IntegerDatabaseTable {
+            name: self.name,
+            connection,
+        })
+    }
+}
+
+impl<'a> IntegerDatabaseTable<'a> {
+    /// Inserts a value under an integer key, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: i64, value: &T) -> Result<usize, Error> {
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} ({}, {}) VALUES(?1, ?2)",
+                self.name, KEY_COLUMN, VALUE_COLUMN
+            ),
+            params![key, value],
+        )
+    }
+
+    /// Get a value by its integer key, see `DatabaseTable::get`
+    pub fn get<T: FromSql>(&self, key: i64) -> Result<T, Error> {
+        self.connection.query_row(
+            &format!(
+                "SELECT {} FROM {} WHERE {} = ?1",
+                VALUE_COLUMN, self.name, KEY_COLUMN
+            ),
+            params![key],
+            |row| row.get::<usize, T>(0),
+        )
+    }
+
+    /// Update a value by its integer key, see `DatabaseTable::update`
+    pub fn update<T: ToSql + ?Sized>(&self, key: i64, value: &T) -> Result<usize, Error> {
+        self.connection.execute(
+            &format!(
+                "UPDATE {} SET {} = ?1, {} = {} WHERE {} = ?2",
+                self.name, VALUE_COLUMN, UPDATED_AT_COLUMN, NOW, KEY_COLUMN
+            ),
+            params![value, key],
+        )
+    }
+
+    /// Remove a value by its integer key, see `DatabaseTable::remove`
+    pub fn remove(&self, key: i64) -> Result<usize, Error> {
+        self.connection.execute(
+            &format!("DELETE FROM {} WHERE {} = ?1", self.name, KEY_COLUMN),
+            params![key],
+        )
+    }
+
+    /// Get every entry with a key in `[start, end)`
+    ///
+    /// Keys are compared numerically and entries are ordered by ascending
+    /// key, `start` is included and `end` isn't.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// let readings = Table::with_integer_key("readings");
+    /// let readings = readings.create(&connection)?;
+    ///
+    /// readings.set(900, &20.0)?;
+    /// readings.set(1000, &21.5)?;
+    /// readings.set(2000, &22.0)?;
+    ///
+    /// assert_eq!(vec![(900, 20.0), (1000, 21.5)], readings.range::<f64>(0, 2000)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(table = self.name)))]
+    pub fn range<T: FromSql>(&self, start: i64, end: i64) -> Result<Vec<(i64, T)>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE {} >= ?1 AND {} < ?2 ORDER BY {}",
+            KEY_COLUMN, VALUE_COLUMN, self.name, KEY_COLUMN, KEY_COLUMN, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map(params![start, end], |row| {
+                Ok((row.get::<usize, i64>(0)?, row.get::<usize, T>(1)?))
+            })?
+            .collect::<Result<Vec<(i64, T)>, Error>>()?;
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::OptionalExtension;
+
+    #[test]
+    fn test_integer_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::with_integer_key("readings");
+        let db = table.create(&conn).unwrap();
+
+        db.set(1, "one").unwrap();
+        db.set(-5, "minus five").unwrap();
+
+        assert_eq!("one", db.get::<String>(1).unwrap());
+        assert_eq!("minus five", db.get::<String>(-5).unwrap());
+        assert!(db.set(1, "again").is_err());
+
+        db.update(1, "uno").unwrap();
+        assert_eq!("uno", db.get::<String>(1).unwrap());
+
+        assert_eq!(1, db.remove(1).unwrap());
+        assert_eq!(None, db.get::<String>(1).optional().unwrap());
+    }
+
+    #[test]
+    fn test_integer_range() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::with_integer_key("readings");
+        let db = table.create(&conn).unwrap();
+
+        // Epoch millis a second apart, inserted out of order
+        let start = 1_700_000_000_000_i64;
+        for i in [9, 3, 10, 0, 5, 1, 2, 8, 4, 7, 6] {
+            db.set(start + i * 1000, &i).unwrap();
+        }
+
+        let window = db.range::<i64>(start + 2000, start + 10_000).unwrap();
+
+        assert_eq!(
+            (2..10).map(|i| (start + i * 1000, i)).collect::<Vec<_>>(),
+            window
+        );
+        assert!(db
+            .range::<i64>(start + 10_001, start + 20_000)
+            .unwrap()
+            .is_empty());
+        assert!(db.range::<i64>(start + 5000, start).unwrap().is_empty());
+    }
+}
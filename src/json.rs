@@ -0,0 +1,717 @@
+use crate::{
+    sanitize_identifier, transaction::Scope, DatabaseTable, KeyValue, Table, KEY_COLUMN,
+    VALUE_COLUMN,
+};
+use rusqlite::{
+    params,
+    types::{self, Type, ValueRef},
+    Connection, Error, OptionalExtension, Result,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, io::Write};
+
+/// Maps a SQLite dynamically typed value to a JSON value
+///
+/// Integers and reals become numbers, text becomes a string and blobs
+/// become an array of bytes. Reals which can't be represented in JSON
+/// (`NaN` and infinities) become `null`.
+pub(crate) fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(bytes) => Value::from(bytes.to_vec()),
+    }
+}
+
+/// Maps a JSON value to the SQLite value it's stored as
+///
+/// The reverse of `to_json` for scalars, booleans become `0` or `1` and
+/// arrays & objects are stored as JSON text.
+pub(crate) fn from_json(value: &Value) -> types::Value {
+    match value {
+        Value::Null => types::Value::Null,
+        Value::Bool(b) => types::Value::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => types::Value::Integer(i),
+            None => types::Value::Real(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(text) => types::Value::Text(text.clone()),
+        value => types::Value::Text(value.to_string()),
+    }
+}
+
+/// The error for a backup document without the expected layout
+fn invalid_backup(reason: &str) -> Error {
+    Error::FromSqlConversionFailure(0, Type::Text, format!("invalid backup: {}", reason).into())
+}
+
+/// Deep merges `patch` into `target`
+///
+/// Objects are merged key by key, recursing into nested objects. Anything
+/// else, scalars, arrays and `null`, replaces the existing value.
+pub(crate) fn merge(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Parses JSON text read from the value column
+pub(crate) fn parse_json(text: &str) -> Result<Value, Error> {
+    serde_json::from_str(text)
+        .map_err(|e| Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+/// Wraps an I/O or serialization error while writing JSON out
+pub(crate) fn write_error(error: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::ToSqlConversionFailure(Box::new(error))
+}
+
+impl<'a> Table<'a> {
+    /// Exports several tables into one JSON document
+    ///
+    /// Produces `{ "table": [{ "key": ..., "value": ... }, ...], ... }`
+    /// with each table's entries converted like `entries_json`, giving a
+    /// single-file backup that `load_all_json` restores. Every name is
+    /// validated with `sanitize_identifier` and the tables are read within
+    /// one transaction so the backup is consistent. Requires the `serde`
+    /// feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// Table::new("users").create(&connection)?.set("jimmy", "abc")?;
+    ///
+    /// let backup = Table::dump_all_json(&connection, &["users"])?;
+    /// assert_eq!(r#"{"users":[{"key":"jimmy","value":"abc"}]}"#, backup);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn dump_all_json(connection: &Connection, names: &[&str]) -> Result<String, Error> {
+        for name in names {
+            sanitize_identifier(name)?;
+        }
+
+        let transaction = Scope::begin(connection)?;
+        let mut tables = serde_json::Map::new();
+
+        for name in names {
+            let entries = Table::existing(name, connection)
+                .entries_json()?
+                .into_iter()
+                .map(|entry| serde_json::json!({ "key": entry.key, "value": entry.value }))
+                .collect();
+
+            tables.insert((*name).to_owned(), Value::Array(entries));
+        }
+
+        transaction.commit()?;
+
+        Ok(Value::Object(tables).to_string())
+    }
+
+    /// Restores tables from a document made by `dump_all_json`
+    ///
+    /// Each table is created if it doesn't exist and its entries inserted,
+    /// all within one transaction so either the whole backup is loaded or,
+    /// if anything fails (e.g. a key that already exists), none of it is.
+    /// JSON is mapped back to SQLite values, arrays & objects (including
+    /// blobs, which export as byte arrays) are stored as JSON text. Returns
+    /// the number of inserted entries. Requires the `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    ///
+    /// let backup = r#"{"users":[{"key":"jimmy","value":"abc"}]}"#;
+    /// assert_eq!(1, Table::load_all_json(&connection, backup)?);
+    ///
+    /// assert_eq!("abc", Table::existing("users", &connection).get::<String>("jimmy")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn load_all_json(connection: &Connection, json: &str) -> Result<usize, Error> {
+        let backup = parse_json(json)?;
+        let tables = backup
+            .as_object()
+            .ok_or_else(|| invalid_backup("expected an object of tables"))?;
+
+        let transaction = Scope::begin(connection)?;
+        let mut inserted = 0;
+
+        for (name, entries) in tables {
+            let table = Table::new(name);
+            table.create(connection)?;
+
+            let entries = entries
+                .as_array()
+                .ok_or_else(|| invalid_backup("expected an array of entries"))?;
+            let mut statement = connection.prepare(&format!(
+                "INSERT INTO {} ({}, {}) VALUES(?1, ?2)",
+                name, KEY_COLUMN, VALUE_COLUMN
+            ))?;
+
+            for entry in entries {
+                let key = entry
+                    .get("key")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid_backup("expected a string key"))?;
+                let value = entry
+                    .get("value")
+                    .ok_or_else(|| invalid_backup("expected a value"))?;
+
+                inserted += statement.execute(params![key, from_json(value)])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(inserted)
+    }
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Get every entry in the table decoded as JSON
+    ///
+    /// Each value is converted to a `serde_json::Value` based on how SQLite
+    /// stored it, regardless of the type used to `set` it. Entries are
+    /// ordered by key. Requires the `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", &42)?;
+    ///
+    /// let entries = table.entries_json()?;
+    /// assert_eq!("jimmy", entries[0].key);
+    /// assert_eq!(serde_json::json!(42), entries[0].value);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn entries_json(&self) -> Result<Vec<KeyValue<Value>>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} ORDER BY {}",
+            KEY_COLUMN, value_column, self.name, KEY_COLUMN
+        ))?;
+        let entries = statement
+            .query_map([], |row| {
+                Ok(KeyValue {
+                    key: row.get::<usize, String>(0)?,
+                    value: to_json(row.get_ref(1)?),
+                })
+            })?
+            .collect::<Result<Vec<KeyValue<Value>>, Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// Writes every entry to `writer` as newline delimited JSON
+    ///
+    /// Each entry is written as `{"key":...,"value":...}` on its own line,
+    /// with values converted like `entries_json`, in key order. Rows are
+    /// streamed to the writer as they are read rather than collected
+    /// first. Returns the number of entries written, failed writes are
+    /// returned as `Error::ToSqlConversionFailure`. Requires the `serde`
+    /// feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    ///
+    /// let mut out = Vec::new();
+    /// table.dump_ndjson(&mut out)?;
+    ///
+    /// assert_eq!(b"{\"key\":\"jimmy\",\"value\":\"abc\"}\n", &out[..]);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn dump_ndjson<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, {} FROM {} ORDER BY {}",
+            KEY_COLUMN, value_column, self.name, KEY_COLUMN
+        ))?;
+        let mut rows = statement.query([])?;
+        let mut count = 0;
+
+        while let Some(row) = rows.next()? {
+            let line = serde_json::json!({
+                "key": row.get::<usize, String>(0)?,
+                "value": to_json(row.get_ref(1)?),
+            });
+
+            serde_json::to_writer(&mut writer, &line).map_err(write_error)?;
+            writer.write_all(b"\n").map_err(write_error)?;
+            count += 1;
+        }
+
+        writer.flush().map_err(write_error)?;
+
+        Ok(count)
+    }
+
+    /// Get several values at once decoded as JSON
+    ///
+    /// Looks up each key and converts its value to a `serde_json::Value`
+    /// like `entries_json`, so values of mixed types can be read in one
+    /// call. Keys missing from the table are left out of the map. Requires
+    /// the `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", "abc")?;
+    /// table.set("age", &42)?;
+    ///
+    /// let values = table.get_many_json(&["jimmy", "age", "missing"])?;
+    /// assert_eq!(serde_json::json!("abc"), values["jimmy"]);
+    /// assert_eq!(serde_json::json!(42), values["age"]);
+    /// assert!(!values.contains_key("missing"));
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn get_many_json(&self, keys: &[&str]) -> Result<HashMap<String, Value>, Error> {
+        let value_column = self.value_sql()?;
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM {} WHERE {} = ?1 LIMIT 1",
+            value_column, self.name, KEY_COLUMN
+        ))?;
+        let mut values = HashMap::with_capacity(keys.len());
+
+        for key in keys {
+            let value = statement
+                .query_row([key], |row| Ok(to_json(row.get_ref(0)?)))
+                .optional()?;
+
+            if let Some(value) = value {
+                values.insert((*key).to_owned(), value);
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Stores every entry of a map as JSON in one transaction
+    ///
+    /// Each value is serialized to JSON text and inserted with its key, if
+    /// any value fails to serialize or any key already exists nothing is
+    /// stored. Returns the number of inserted rows. Requires the `serde`
+    /// feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use std::collections::HashMap;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("config").create(&connection)?;
+    /// let table = Table::existing("config", &connection);
+    ///
+    /// let mut config = HashMap::new();
+    /// config.insert("retries".to_owned(), vec![1, 2, 4]);
+    ///
+    /// assert_eq!(1, table.set_all_json(&config)?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(table = self.name)))]
+    pub fn set_all_json<T: Serialize>(&self, map: &HashMap<String, T>) -> Result<usize, Error> {
+        let entries = map
+            .iter()
+            .map(|(key, value)| {
+                serde_json::to_string(value)
+                    .map(|text| (key, text))
+                    .map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))
+            })
+            .collect::<Result<Vec<(&String, String)>, Error>>()?;
+
+        self.set_iter(entries)
+    }
+
+    /// Deep merges a JSON patch into a stored JSON object
+    ///
+    /// The value is read as JSON text, merged with `patch` and written back
+    /// within one transaction. Nested objects are merged recursively while
+    /// scalars and arrays in the patch replace the stored value. Fails if
+    /// the key is missing or the stored value isn't JSON. Requires the
+    /// `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use serde_json::json;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", &json!({ "name": "jimmy", "prefs": { "theme": "dark" } }).to_string())?;
+    /// table.merge_json("jimmy", &json!({ "prefs": { "lang": "en" } }))?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, patch), fields(table = self.name)))]
+    pub fn merge_json(&self, key: &str, patch: &Value) -> Result<(), Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let mut value = parse_json(&self.read::<String>(key)?)?;
+        merge(&mut value, patch);
+        self.update(key, &value.to_string())?;
+
+        transaction.commit()
+    }
+
+    /// Appends an item to a stored JSON array
+    ///
+    /// Reads the array stored under `key`, starting a new one if the key is
+    /// missing, appends `item` and writes it back within one transaction.
+    /// Returns the new length of the array. Fails if the stored value isn't
+    /// a JSON array of `T`. Requires the `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("logs").create(&connection)?;
+    /// let table = Table::existing("logs", &connection);
+    ///
+    /// table.push_json("jimmy", &"signed in".to_owned())?;
+    ///
+    /// assert_eq!(2, table.push_json("jimmy", &"signed out".to_owned())?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, item), fields(table = self.name)))]
+    pub fn push_json<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &str,
+        item: &T,
+    ) -> Result<usize, Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let stored = self.read::<String>(key).optional()?;
+        let items = match &stored {
+            Some(text) => serde_json::from_str::<Vec<T>>(text)
+                .map_err(|e| Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?,
+            None => Vec::new(),
+        };
+        let mut refs = items.iter().collect::<Vec<&T>>();
+        refs.push(item);
+
+        let text =
+            serde_json::to_string(&refs).map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+        match stored {
+            Some(_) => self.update(key, &text)?,
+            None => self.set(key, &text)?,
+        };
+
+        transaction.commit()?;
+
+        Ok(refs.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::Connection;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_entries_json() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("a", &123).unwrap();
+        db.set("b", "abc").unwrap();
+        db.set("c", &rusqlite::types::Null).unwrap();
+        db.set("d", &1.5).unwrap();
+
+        let entries: Vec<(String, serde_json::Value)> = db
+            .entries_json()
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("a".to_owned(), json!(123)),
+                ("b".to_owned(), json!("abc")),
+                ("c".to_owned(), json!(null)),
+                ("d".to_owned(), json!(1.5)),
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn test_get_many_json() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("name", "jimmy").unwrap();
+        db.set("age", &42).unwrap();
+        db.set("other", "ignored").unwrap();
+
+        let values = db.get_many_json(&["name", "age", "missing"]).unwrap();
+
+        assert_eq!(2, values.len());
+        assert_eq!(json!("jimmy"), values["name"]);
+        assert_eq!(json!(42), values["age"]);
+        assert!(!values.contains_key("missing"));
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let stored = json!({
+            "name": "jimmy",
+            "tags": ["a", "b"],
+            "prefs": { "theme": "dark", "font": { "size": 12, "family": "mono" } }
+        });
+        db.set("jimmy", &stored.to_string()).unwrap();
+
+        db.merge_json(
+            "jimmy",
+            &json!({
+                "tags": ["c"],
+                "prefs": { "lang": "en", "font": { "size": 14 } }
+            }),
+        )
+        .unwrap();
+
+        let merged: serde_json::Value =
+            serde_json::from_str(&db.get::<String>("jimmy").unwrap()).unwrap();
+
+        assert_eq!(
+            json!({
+                "name": "jimmy",
+                "tags": ["c"],
+                "prefs": {
+                    "theme": "dark",
+                    "lang": "en",
+                    "font": { "size": 14, "family": "mono" }
+                }
+            }),
+            merged
+        );
+    }
+
+    #[test]
+    fn test_merge_json_invalid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("text", "not json").unwrap();
+
+        assert!(db.merge_json("text", &json!({ "a": 1 })).is_err());
+        assert!(db.merge_json("missing", &json!({ "a": 1 })).is_err());
+        assert_eq!("not json", db.get::<String>("text").unwrap());
+    }
+
+    #[test]
+    fn test_push_json() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        assert_eq!(1, db.push_json("jimmy", &1).unwrap());
+        assert_eq!(2, db.push_json("jimmy", &2).unwrap());
+        assert_eq!(3, db.push_json("jimmy", &3).unwrap());
+
+        let items: Vec<i32> = serde_json::from_str(&db.get::<String>("jimmy").unwrap()).unwrap();
+        assert_eq!(vec![1, 2, 3], items);
+    }
+
+    #[test]
+    fn test_push_json_not_an_array() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("object", &json!({ "a": 1 }).to_string()).unwrap();
+        db.set("strings", &json!(["a"]).to_string()).unwrap();
+
+        assert!(db.push_json("object", &1).is_err());
+        assert!(db.push_json("strings", &1).is_err());
+        assert_eq!(r#"["a"]"#, db.get::<String>("strings").unwrap());
+    }
+
+    #[test]
+    fn test_set_all_json() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Service {
+            host: String,
+            port: u16,
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("config");
+        let db = table.create(&conn).unwrap();
+
+        let mut services = HashMap::new();
+        services.insert(
+            "api".to_owned(),
+            Service {
+                host: "localhost".to_owned(),
+                port: 8080,
+            },
+        );
+        services.insert(
+            "db".to_owned(),
+            Service {
+                host: "10.0.0.2".to_owned(),
+                port: 5432,
+            },
+        );
+
+        assert_eq!(2, db.set_all_json(&services).unwrap());
+
+        for (key, service) in &services {
+            let stored: Service = serde_json::from_str(&db.get::<String>(key).unwrap()).unwrap();
+            assert_eq!(service, &stored);
+        }
+
+        // Existing keys fail the whole map
+        assert!(db.set_all_json(&services).is_err());
+        assert_eq!(2, db.count().unwrap());
+    }
+
+    #[test]
+    fn test_dump_ndjson() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("b", "abc").unwrap();
+        db.set("a", &123).unwrap();
+        db.set("c", &rusqlite::types::Null).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(3, db.dump_ndjson(&mut out).unwrap());
+
+        let lines = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                json!({ "key": "a", "value": 123 }),
+                json!({ "key": "b", "value": "abc" }),
+                json!({ "key": "c", "value": null }),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_dump_and_load_all_json() {
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        let settings = Table::new("settings");
+        let settings = settings.create(&conn).unwrap();
+
+        users.set("jimmy", "abc").unwrap();
+        users.set("bob", &42).unwrap();
+        settings.set("ratio", &1.5).unwrap();
+        settings
+            .set("theme", &json!({ "dark": true }).to_string())
+            .unwrap();
+        settings.set("none", &rusqlite::types::Null).unwrap();
+
+        let backup = Table::dump_all_json(&conn, &["users", "settings"]).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&backup).unwrap();
+        assert_eq!(
+            json!([{ "key": "bob", "value": 42 }, { "key": "jimmy", "value": "abc" }]),
+            parsed["users"]
+        );
+
+        let restored = Connection::open_in_memory().unwrap();
+        assert_eq!(5, Table::load_all_json(&restored, &backup).unwrap());
+
+        let users = Table::existing("users", &restored);
+        let settings = Table::existing("settings", &restored);
+        assert_eq!("abc", users.get::<String>("jimmy").unwrap());
+        assert_eq!(42, users.get::<i64>("bob").unwrap());
+        assert_eq!(1.5, settings.get::<f64>("ratio").unwrap());
+        assert_eq!(
+            json!({ "dark": true }).to_string(),
+            settings.get::<String>("theme").unwrap()
+        );
+        assert_eq!(None, settings.get::<Option<i64>>("none").unwrap());
+        assert_eq!(
+            backup,
+            Table::dump_all_json(&restored, &["users", "settings"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_load_all_json_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        users.set("jimmy", "abc").unwrap();
+
+        let backup = json!({
+            "posts": [{ "key": "first", "value": "hello" }],
+            "users": [{ "key": "jimmy", "value": "duplicate" }]
+        });
+
+        assert!(Table::load_all_json(&conn, &backup.to_string()).is_err());
+        assert!(!Table::is_compatible("posts", &conn).unwrap());
+        assert!(Table::load_all_json(&conn, "[]").is_err());
+        assert!(Table::load_all_json(&conn, r#"{"users":[{"value":1}]}"#).is_err());
+        assert!(Table::load_all_json(&conn, r#"{"bad name":[]}"#).is_err());
+        assert!(Table::dump_all_json(&conn, &["users; DROP TABLE users"]).is_err());
+        assert_eq!("abc", users.get::<String>("jimmy").unwrap());
+    }
+}
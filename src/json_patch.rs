@@ -0,0 +1,261 @@
+use crate::{json::parse_json, transaction::Scope, DatabaseTable};
+use rusqlite::{Error, Result};
+use serde_json::Value;
+
+/// The error for a patch that is malformed or can't be applied
+fn patch_error(index: usize, reason: &str) -> Error {
+    Error::ToSqlConversionFailure(
+        format!("json patch operation {} failed: {}", index, reason).into(),
+    )
+}
+
+/// Splits a JSON pointer into its parent pointer and unescaped last token
+fn split_pointer(path: &str) -> Option<(&str, String)> {
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let (parent, last) = path.rsplit_once('/')?;
+
+    Some((parent, last.replace("~1", "/").replace("~0", "~")))
+}
+
+/// Parses an array index token, which must be below `bound`
+fn parse_index(token: &str, bound: usize) -> Option<usize> {
+    let valid =
+        token.bytes().all(|b| b.is_ascii_digit()) && (token == "0" || !token.starts_with('0'));
+
+    token.parse().ok().filter(|index| valid && *index < bound)
+}
+
+/// Adds `value` at `path`, inserting into arrays and objects
+fn add(document: &mut Value, path: &str, value: Value) -> Option<()> {
+    if path.is_empty() {
+        *document = value;
+        return Some(());
+    }
+
+    let (parent, token) = split_pointer(path)?;
+
+    match document.pointer_mut(parent)? {
+        Value::Object(map) => {
+            map.insert(token, value);
+        }
+        Value::Array(items) if token == "-" => items.push(value),
+        Value::Array(items) => {
+            let index = parse_index(&token, items.len() + 1)?;
+            items.insert(index, value);
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Removes and returns the value at `path`
+fn remove(document: &mut Value, path: &str) -> Option<Value> {
+    let (parent, token) = split_pointer(path)?;
+
+    match document.pointer_mut(parent)? {
+        Value::Object(map) => map.remove(&token),
+        Value::Array(items) => {
+            let index = parse_index(&token, items.len())?;
+            Some(items.remove(index))
+        }
+        _ => None,
+    }
+}
+
+/// Applies a single RFC 6902 operation to `document`
+fn apply_operation(document: &mut Value, operation: &Value) -> Result<(), &'static str> {
+    let field = |name: &str| operation.get(name).ok_or("missing field");
+    let pointer = |name: &str| field(name)?.as_str().ok_or("pointer isn't a string");
+
+    let path = pointer("path")?;
+
+    match field("op")?.as_str() {
+        Some("add") => add(document, path, field("value")?.clone()).ok_or("path not found"),
+        Some("remove") => remove(document, path).map(|_| ()).ok_or("path not found"),
+        Some("replace") => {
+            let value = field("value")?.clone();
+            *document.pointer_mut(path).ok_or("path not found")? = value;
+            Ok(())
+        }
+        Some("move") => {
+            let from = pointer("from")?;
+            if path.starts_with(&format!("{}/", from)) {
+                return Err("can't move a value into one of its children");
+            }
+
+            let value = remove(document, from).ok_or("from not found")?;
+            add(document, path, value).ok_or("path not found")
+        }
+        Some("copy") => {
+            let value = document
+                .pointer(pointer("from")?)
+                .cloned()
+                .ok_or("from not found")?;
+            add(document, path, value).ok_or("path not found")
+        }
+        Some("test") => match document.pointer(path) {
+            Some(value) if value == field("value")? => Ok(()),
+            _ => Err("test failed"),
+        },
+        _ => Err("unknown op"),
+    }
+}
+
+/// Applies an RFC 6902 JSON Patch to `document`
+///
+/// Operations are applied in order, stopping at the first that fails.
+pub(crate) fn apply_patch(document: &mut Value, patch: &Value) -> Result<(), Error> {
+    let operations = patch
+        .as_array()
+        .ok_or_else(|| patch_error(0, "patch isn't an array"))?;
+
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(document, operation).map_err(|reason| patch_error(index, reason))?;
+    }
+
+    Ok(())
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Applies a JSON Patch (RFC 6902) to a stored JSON value
+    ///
+    /// Supports the `add`, `remove`, `replace`, `move`, `copy` & `test`
+    /// operations. The value is read as JSON text, patched and written back
+    /// within one transaction. If any operation fails, including a `test`
+    /// whose value doesn't match, the whole patch is aborted and the stored
+    /// value is left unchanged. Fails if the key is missing or the stored
+    /// value isn't JSON. Requires the `serde` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use serde_json::json;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set("jimmy", &json!({ "name": "jimmy", "tags": [] }).to_string())?;
+    /// table.apply_json_patch(
+    ///     "jimmy",
+    ///     &json!([
+    ///         { "op": "test", "path": "/name", "value": "jimmy" },
+    ///         { "op": "add", "path": "/tags/-", "value": "admin" }
+    ///     ]),
+    /// )?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, patch), fields(table = self.name)))]
+    pub fn apply_json_patch(&self, key: &str, patch: &Value) -> Result<(), Error> {
+        let transaction = Scope::begin(self.connection)?;
+
+        let mut value = parse_json(&self.read::<String>(key)?)?;
+        apply_patch(&mut value, patch)?;
+        self.update(key, &value.to_string())?;
+
+        transaction.commit()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_json_patch_replace() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", &json!({ "name": "jimmy", "age": 30 }).to_string())
+            .unwrap();
+
+        db.apply_json_patch(
+            "jimmy",
+            &json!([{ "op": "replace", "path": "/age", "value": 31 }]),
+        )
+        .unwrap();
+
+        let patched: Value = serde_json::from_str(&db.get::<String>("jimmy").unwrap()).unwrap();
+        assert_eq!(json!({ "name": "jimmy", "age": 31 }), patched);
+    }
+
+    #[test]
+    fn test_apply_json_patch_failed_test_aborts() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let stored = json!({ "name": "jimmy", "age": 30 }).to_string();
+        db.set("jimmy", &stored).unwrap();
+
+        let result = db.apply_json_patch(
+            "jimmy",
+            &json!([
+                { "op": "replace", "path": "/age", "value": 31 },
+                { "op": "test", "path": "/name", "value": "bob" }
+            ]),
+        );
+
+        match result {
+            Err(Error::ToSqlConversionFailure(e)) => {
+                assert_eq!("json patch operation 1 failed: test failed", e.to_string())
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(stored, db.get::<String>("jimmy").unwrap());
+        assert!(db.apply_json_patch("missing", &json!([])).is_err());
+    }
+
+    #[test]
+    fn test_apply_patch_operations() {
+        let mut document = json!({ "a/b": 1, "list": [1, 2, 3], "nested": { "x": "y" } });
+
+        apply_patch(
+            &mut document,
+            &json!([
+                { "op": "add", "path": "/list/1", "value": 9 },
+                { "op": "add", "path": "/list/-", "value": 4 },
+                { "op": "remove", "path": "/a~1b" },
+                { "op": "copy", "from": "/nested", "path": "/copied" },
+                { "op": "move", "from": "/nested/x", "path": "/moved" },
+                { "op": "test", "path": "/copied/x", "value": "y" }
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json!({
+                "list": [1, 9, 2, 3, 4],
+                "nested": {},
+                "copied": { "x": "y" },
+                "moved": "y"
+            }),
+            document
+        );
+
+        let invalid = [
+            json!({ "op": "add", "path": "/list/01", "value": 0 }),
+            json!({ "op": "add", "path": "/list/9", "value": 0 }),
+            json!({ "op": "remove", "path": "/missing" }),
+            json!({ "op": "replace", "path": "/missing", "value": 0 }),
+            json!({ "op": "move", "from": "/nested", "path": "/nested/inner" }),
+            json!({ "op": "copy", "from": "/missing", "path": "/copy" }),
+            json!({ "op": "unknown", "path": "/list" }),
+            json!({ "op": "add", "path": "list", "value": 0 }),
+        ];
+
+        for operation in invalid {
+            assert!(apply_patch(&mut document.clone(), &json!([operation])).is_err());
+        }
+    }
+}
@@ -1,3 +1,78 @@
+#[cfg(feature = "actix")]
+mod actix;
+pub mod affinity;
+pub mod batch;
+#[cfg(feature = "blob")]
+mod blob;
+#[cfg(feature = "lru")]
+pub mod cached;
+pub mod composite;
+#[cfg(feature = "compression")]
+mod compress;
 pub mod db;
+pub mod entry;
+mod eviction;
+mod expiry;
+#[cfg(feature = "async")]
+mod fetch;
+#[cfg(feature = "fts")]
+mod fts;
+#[cfg(feature = "functions")]
+mod functions;
+pub mod instrumented;
+pub mod integer;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+mod json_patch;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+pub mod owned;
+#[cfg(feature = "r2d2")]
+pub mod pool;
+pub mod shared;
+pub mod transaction;
+#[cfg(feature = "uuid")]
+mod uuid;
+pub mod value;
+#[cfg(feature = "watch")]
+pub mod watch;
 
+pub use affinity::*;
+pub use batch::*;
+#[cfg(feature = "lru")]
+pub use cached::*;
+pub use composite::*;
 pub use db::*;
+pub use entry::*;
+pub use instrumented::*;
+pub use integer::*;
+pub use owned::*;
+#[cfg(feature = "r2d2")]
+pub use pool::*;
+pub use shared::*;
+pub use transaction::*;
+pub use value::*;
+#[cfg(feature = "watch")]
+pub use watch::*;
+
+/// The error returned by adrodb operations, re-exported from rusqlite
+pub use rusqlite::Error;
+
+/// A `Result` with adrodb's `Error`
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::Table;
+/// use rusqlite::Connection;
+///
+/// fn add_user(connection: &Connection, name: &str) -> adrodb::Result<usize> {
+///     Table::existing_or_create("users", connection)?.set(name, "active")
+/// }
+///
+/// let connection = Connection::open_in_memory()?;
+/// assert_eq!(1, add_user(&connection, "jimmy")?);
+/// # Ok::<(), adrodb::Error>(())
+/// ```
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,88 @@
+use crate::DatabaseTable;
+use rusqlite::{types::Type, Error, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+impl<'a> DatabaseTable<'a> {
+    /// Inserts a value serialized as MessagePack
+    ///
+    /// The value is encoded with [rmp-serde](https://docs.rs/rmp-serde) and
+    /// stored as a BLOB, which is more compact than JSON for nested data.
+    /// Requires the `msgpack` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.set_msgpack("jimmy", &vec![1, 2, 3])?;
+    ///
+    /// let value = table.get_msgpack::<Vec<i32>>("jimmy")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn set_msgpack<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        let bytes = rmp_serde::to_vec_named(value)
+            .map_err(|e| Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        self.set(key, &bytes)
+    }
+
+    /// Get a value stored as MessagePack by key
+    ///
+    /// Reads the BLOB written by `set_msgpack` and decodes it into `T`.
+    /// Requires the `msgpack` feature.
+    pub fn get_msgpack<T: DeserializeOwned>(&self, key: &str) -> Result<T, Error> {
+        let bytes = self.get::<Vec<u8>>(key)?;
+
+        rmp_serde::from_slice(&bytes)
+            .map_err(|e| Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::Connection;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        name: String,
+        age: u8,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let user = User {
+            name: "jimmy".to_owned(),
+            age: 42,
+            tags: vec!["admin".to_owned(), "beans".to_owned()],
+        };
+
+        db.set_msgpack("jimmy", &user).unwrap();
+
+        let result = db.get_msgpack::<User>("jimmy").unwrap();
+        assert_eq!(user, result);
+    }
+
+    #[test]
+    fn test_msgpack_invalid_data() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        db.set("jimmy", "not msgpack").unwrap();
+
+        let result = db.get_msgpack::<User>("jimmy");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,615 @@
+use crate::{sanitize_identifier, DatabaseTable, Table};
+use rusqlite::{ffi, Connection, Error, OpenFlags, Result, TransactionBehavior};
+use std::path::Path;
+
+/// Opens and sets up the connections used by the `Table` builder
+///
+/// Implement `setup` to run extension loading or pragmas on each new
+/// connection, or override `open` to control how the connection itself is
+/// opened. Closures taking `&Connection` implement the trait as `setup`.
+pub trait ConnectionFactory {
+    /// Opens a connection to the database at `path`
+    ///
+    /// Defaults to `Connection::open` followed by `setup`.
+    fn open(&self, path: &Path) -> Result<Connection, Error> {
+        let connection = Connection::open(path)?;
+        self.setup(&connection)?;
+
+        Ok(connection)
+    }
+
+    /// Runs custom setup on a newly opened connection
+    fn setup(&self, _connection: &Connection) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: Fn(&Connection) -> Result<(), Error>> ConnectionFactory for F {
+    fn setup(&self, connection: &Connection) -> Result<(), Error> {
+        self(connection)
+    }
+}
+
+/// A schema migration run by `Table::open_and_migrate`
+///
+/// Migration `n` in the list upgrades a database from version `n` to
+/// `n + 1`.
+pub type Migration = fn(&Connection) -> Result<(), Error>;
+
+/// A table that owns its connection
+///
+/// `DatabaseTable` borrows its connection so it can't be returned together
+/// with the connection it was created from. `OwnedTable` holds both and
+/// hands out a `DatabaseTable` to perform operations with.
+#[derive(Debug)]
+pub struct OwnedTable {
+    name: String,
+    connection: Connection,
+}
+
+impl OwnedTable {
+    /// Creates a new owned table from a connection
+    ///
+    /// Like `Table::existing` this doesn't run a `CREATE` command, the
+    /// table is expected to exist already.
+    pub fn new(name: &str, connection: Connection) -> Self {
+        OwnedTable {
+            name: name.to_owned(),
+            connection,
+        }
+    }
+
+    /// Returns a `DatabaseTable` to perform operations on the table
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{OwnedTable, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// Table::new("users").create(&connection)?;
+    /// let owned = OwnedTable::new("users", connection);
+    ///
+    /// owned.table().set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn table(&self) -> DatabaseTable<'_> {
+        Table::existing(&self.name, &self.connection)
+    }
+
+    /// Returns the name of the table
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a reference to the owned connection
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Consumes the table and returns the owned connection
+    ///
+    /// Lets the connection be reused, e.g. for another table, rather than
+    /// dropping and reopening it.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{OwnedTable, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let owned = OwnedTable::new("users", Connection::open_in_memory()?);
+    /// let connection = owned.into_connection();
+    ///
+    /// let posts = Table::existing_or_create("posts", &connection)?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn into_connection(self) -> Connection {
+        self.connection
+    }
+
+    /// Closes the owned connection
+    ///
+    /// Dropping an `OwnedTable` closes its connection but silently ignores
+    /// any failure. This surfaces the error instead, handing the connection
+    /// back so the caller can retry, e.g. once outstanding statements are
+    /// finished.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::OwnedTable;
+    /// use rusqlite::Connection;
+    ///
+    /// let owned = OwnedTable::new("users", Connection::open_in_memory()?);
+    ///
+    /// if let Err((connection, err)) = owned.close() {
+    ///     eprintln!("Unable to close the connection: {}", err);
+    /// }
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn close(self) -> Result<(), (Connection, Error)> {
+        self.connection.close()
+    }
+}
+
+impl<'a> Table<'a> {
+    /// Opens a database and creates the table, returning an `OwnedTable`
+    ///
+    /// The database file is created if it doesn't exist. The connection is
+    /// opened through the builder's `ConnectionFactory` when one is set,
+    /// then builder options such as pragmas are applied to it.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Synchronous, Table};
+    ///
+    /// let owned = Table::new("users")
+    ///     .synchronous(Synchronous::Normal)
+    ///     .open(":memory:")?;
+    ///
+    /// owned.table().set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<OwnedTable, Error> {
+        let connection = match &self.connection_factory {
+            Some(factory) => factory.open(path.as_ref())?,
+            None => Connection::open(path)?,
+        };
+        self.create(&connection)?;
+
+        Ok(OwnedTable::new(self.name, connection))
+    }
+
+    /// Opens the process wide shared in-memory database
+    ///
+    /// Connects to `file::memory:?cache=shared`, so every connection opened
+    /// this way sees the same in-memory database, useful for tests that need
+    /// several connections to the same state. The database only lives while
+    /// at least one of its connections is open. Returns an `OwnedTable`
+    /// since the table can't outlive the connection it borrows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    ///
+    /// let writer = Table::open_in_memory_shared("doc_shared_users")?;
+    /// let reader = Table::open_in_memory_shared("doc_shared_users")?;
+    ///
+    /// writer.table().set("jimmy", "abc")?;
+    /// assert_eq!("abc", reader.table().get::<String>("jimmy")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open_in_memory_shared(name: &str) -> Result<OwnedTable, Error> {
+        Table::new(name).open("file::memory:?cache=shared")
+    }
+
+    /// Opens a database with custom `OpenFlags` and returns an `OwnedTable`
+    ///
+    /// Exposes rusqlite's
+    /// [`OpenFlags`](https://docs.rs/rusqlite/latest/rusqlite/struct.OpenFlags.html)
+    /// for control over how the connection is opened, e.g. read-only,
+    /// no-mutex or shared-cache. The table is created if it doesn't exist
+    /// when the flags allow writing, read-only connections expect the
+    /// table to exist already.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::OpenFlags;
+    ///
+    /// let flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+    /// let owned = Table::open_with_flags(":memory:", "users", flags)?;
+    ///
+    /// owned.table().set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open_with_flags<P: AsRef<Path>>(
+        path: P,
+        name: &str,
+        flags: OpenFlags,
+    ) -> Result<OwnedTable, Error> {
+        let connection = Connection::open_with_flags(path, flags)?;
+
+        if flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE) {
+            Table::new(name).create(&connection)?;
+        }
+
+        Ok(OwnedTable::new(name, connection))
+    }
+
+    /// Opens a database from a `sqlite://` connection string
+    ///
+    /// Lets the database be configured from an environment variable or
+    /// config file, e.g. `sqlite://./data.db?mode=rwc&cache=shared`. The
+    /// path follows `sqlite://`, so `sqlite:///var/data.db` is absolute and
+    /// `sqlite://:memory:` is an in-memory database. Supported query
+    /// parameters:
+    ///
+    /// - `mode`: `ro` read-only, `rw` read-write, `rwc` read-write and
+    ///   create (the default) or `memory` for an in-memory database
+    /// - `cache`: `shared` or `private`
+    ///
+    /// Any other parameter is run as a pragma on the new connection, e.g.
+    /// `journal_mode=wal` or `busy_timeout=5000`. Percent-encoding isn't
+    /// decoded. As with `open_with_flags` the table is created unless the
+    /// connection is read-only. Returns an `OwnedTable` since the table
+    /// can't outlive the connection it borrows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    ///
+    /// let owned = Table::from_url("sqlite://:memory:?journal_mode=memory", "users")?;
+    ///
+    /// owned.table().set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn from_url(url: &str, name: &str) -> Result<OwnedTable, Error> {
+        let (path, flags, pragmas) = parse_url(url)?;
+        let connection = Connection::open_with_flags(path, flags)?;
+
+        for (pragma, value) in pragmas {
+            connection.pragma_update(None, sanitize_identifier(pragma)?, value)?;
+        }
+
+        if flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE) {
+            Table::new(name).create(&connection)?;
+        }
+
+        Ok(OwnedTable::new(name, connection))
+    }
+}
+
+impl<'a> Table<'a> {
+    /// Opens a database, creates the table and applies pending migrations
+    ///
+    /// The schema version is tracked in SQLite's `user_version` pragma,
+    /// a new database starts at version 0. Every migration after the
+    /// current version is run in order, then `user_version` is set to the
+    /// number of migrations. Creating the table, the migrations and the
+    /// version bump share one immediate transaction, so if any migration
+    /// fails the database is left untouched. Errors if the database is at
+    /// a version newer than the migrations know about. Returns an
+    /// `OwnedTable` since the table can't outlive the connection it
+    /// borrows.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{Migration, Table};
+    ///
+    /// let migrations: &[Migration] = &[
+    ///     |connection| connection.execute_batch("CREATE INDEX users_v ON users (v)"),
+    /// ];
+    /// let owned = Table::open_and_migrate(":memory:", "users", migrations)?;
+    ///
+    /// owned.table().set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open_and_migrate<P: AsRef<Path>>(
+        path: P,
+        name: &str,
+        migrations: &[Migration],
+    ) -> Result<OwnedTable, Error> {
+        let mut connection = Connection::open(path)?;
+
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: usize =
+            transaction.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        if version > migrations.len() {
+            return Err(Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_ERROR),
+                Some(format!(
+                    "database is at version {} but only {} migrations are known",
+                    version,
+                    migrations.len()
+                )),
+            ));
+        }
+
+        Table::new(name).create(&transaction)?;
+        for migration in &migrations[version..] {
+            migration(&transaction)?;
+        }
+        transaction.pragma_update(None, "user_version", migrations.len())?;
+        transaction.commit()?;
+
+        Ok(OwnedTable::new(name, connection))
+    }
+}
+
+/// The error returned for a malformed connection string
+fn invalid_url(url: &str) -> Error {
+    Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_MISUSE),
+        Some(format!("invalid url: {:?}", url)),
+    )
+}
+
+/// Pragma names and values taken from a connection string
+type Pragmas<'u> = Vec<(&'u str, &'u str)>;
+
+/// Splits a `sqlite://` url into its path, open flags and pragmas
+fn parse_url(url: &str) -> Result<(&str, OpenFlags, Pragmas<'_>), Error> {
+    let rest = url
+        .strip_prefix("sqlite://")
+        .ok_or_else(|| invalid_url(url))?;
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    if path.is_empty() {
+        return Err(invalid_url(url));
+    }
+
+    let mut flags = OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let mut mode = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+    let mut pragmas = Vec::new();
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').ok_or_else(|| invalid_url(url))?;
+
+        match (key, value) {
+            ("mode", "ro") => mode = OpenFlags::SQLITE_OPEN_READ_ONLY,
+            ("mode", "rw") => mode = OpenFlags::SQLITE_OPEN_READ_WRITE,
+            ("mode", "rwc") => {
+                mode = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            }
+            ("mode", "memory") => {
+                mode = OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | OpenFlags::SQLITE_OPEN_CREATE
+                    | OpenFlags::SQLITE_OPEN_MEMORY
+            }
+            ("cache", "shared") => flags |= OpenFlags::SQLITE_OPEN_SHARED_CACHE,
+            ("cache", "private") => flags |= OpenFlags::SQLITE_OPEN_PRIVATE_CACHE,
+            ("mode" | "cache", _) => return Err(invalid_url(url)),
+            _ => pragmas.push((key, value)),
+        }
+    }
+
+    Ok((path, flags | mode, pragmas))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_open_with_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open_with_flags.sqlite");
+
+        let flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+        let owned = Table::open_with_flags(&path, "users", flags).unwrap();
+
+        owned.table().set("jimmy", "abc").unwrap();
+        assert_eq!("abc", owned.table().get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_open_with_flags_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open_with_flags_read_only.sqlite");
+
+        let owned = Table::open_with_flags(&path, "users", OpenFlags::default()).unwrap();
+        owned.table().set("jimmy", "abc").unwrap();
+        drop(owned);
+
+        let owned =
+            Table::open_with_flags(&path, "users", OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+
+        assert_eq!("abc", owned.table().get::<String>("jimmy").unwrap());
+        assert!(owned.table().set("bob", "def").is_err());
+    }
+
+    #[test]
+    fn test_into_connection() {
+        let owned = Table::open_with_flags(":memory:", "users", OpenFlags::default()).unwrap();
+        owned.table().set("jimmy", "abc").unwrap();
+
+        let connection = owned.into_connection();
+
+        let posts = Table::existing_or_create("posts", &connection).unwrap();
+        posts.set("first", "hello").unwrap();
+
+        let users = Table::existing("users", &connection);
+        assert_eq!("abc", users.get::<String>("jimmy").unwrap());
+        assert_eq!("hello", posts.get::<String>("first").unwrap());
+    }
+
+    #[test]
+    fn test_close() {
+        let owned = Table::open_with_flags(":memory:", "users", OpenFlags::default()).unwrap();
+        owned.table().set("jimmy", "abc").unwrap();
+
+        assert!(owned.close().is_ok());
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open.sqlite");
+
+        let owned = Table::new("users").open(&path).unwrap();
+        owned.table().set("jimmy", "abc").unwrap();
+        drop(owned);
+
+        let owned = Table::new("users").open(&path).unwrap();
+        assert_eq!("abc", owned.table().get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_connection_factory_setup() {
+        let owned = Table::new("users")
+            .connection_factory(|connection: &Connection| {
+                connection.pragma_update(None, "foreign_keys", true)
+            })
+            .open(":memory:")
+            .unwrap();
+
+        let foreign_keys: bool = owned
+            .connection()
+            .pragma_query_value(None, "foreign_keys", |row| row.get(0))
+            .unwrap();
+
+        assert!(foreign_keys);
+        owned.table().set("jimmy", "abc").unwrap();
+    }
+
+    #[test]
+    fn test_connection_factory_open() {
+        struct ReadOnly;
+
+        impl ConnectionFactory for ReadOnly {
+            fn open(&self, path: &Path) -> Result<Connection, Error> {
+                Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("connection_factory_open.sqlite");
+
+        // Neither the database nor the table can be created read only
+        assert!(Table::new("users")
+            .connection_factory(ReadOnly)
+            .open(&path)
+            .is_err());
+    }
+
+    #[test]
+    fn test_open_in_memory_shared() {
+        let first = Table::open_in_memory_shared("test_shared_users").unwrap();
+        let second = Table::open_in_memory_shared("test_shared_users").unwrap();
+
+        first.table().set("jimmy", "abc").unwrap();
+        second.table().set("bob", "def").unwrap();
+
+        assert_eq!("abc", second.table().get::<String>("jimmy").unwrap());
+        assert_eq!("def", first.table().get::<String>("bob").unwrap());
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let (path, flags, pragmas) =
+            parse_url("sqlite://./data.db?mode=ro&cache=shared&journal_mode=wal").unwrap();
+
+        assert_eq!("./data.db", path);
+        assert!(flags.contains(OpenFlags::SQLITE_OPEN_READ_ONLY));
+        assert!(flags.contains(OpenFlags::SQLITE_OPEN_SHARED_CACHE));
+        assert!(!flags.contains(OpenFlags::SQLITE_OPEN_CREATE));
+        assert_eq!(vec![("journal_mode", "wal")], pragmas);
+
+        let (path, flags, pragmas) = parse_url("sqlite:///var/data.db").unwrap();
+
+        assert_eq!("/var/data.db", path);
+        assert!(flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE));
+        assert!(pragmas.is_empty());
+
+        assert!(parse_url("postgres://localhost/data").is_err());
+        assert!(parse_url("sqlite://").is_err());
+        assert!(parse_url("sqlite://data.db?mode=rwx").is_err());
+        assert!(parse_url("sqlite://data.db?journal_mode").is_err());
+    }
+
+    #[test]
+    fn test_from_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("from_url.sqlite");
+
+        let url = format!(
+            "sqlite://{}?mode=rwc&cache=shared&user_version=7",
+            path.display()
+        );
+        let owned = Table::from_url(&url, "users").unwrap();
+        owned.table().set("jimmy", "abc").unwrap();
+
+        let user_version: i64 = owned
+            .connection()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(7, user_version);
+        assert!(path.exists());
+        drop(owned);
+
+        let url = format!("sqlite://{}?mode=ro", path.display());
+        let owned = Table::from_url(&url, "users").unwrap();
+
+        assert_eq!("abc", owned.table().get::<String>("jimmy").unwrap());
+        assert!(owned.table().set("bob", "def").is_err());
+    }
+
+    #[test]
+    fn test_from_url_invalid_pragma() {
+        assert!(Table::from_url("sqlite://:memory:?drop;table=1", "users").is_err());
+    }
+
+    fn user_version(connection: &Connection) -> usize {
+        connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_open_and_migrate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open_and_migrate.sqlite");
+
+        // A version 0 database written before migrations existed
+        let connection = Connection::open(&path).unwrap();
+        let users = Table::new("users").create(&connection).unwrap();
+        users.set("jimmy", "abc").unwrap();
+        drop(connection);
+
+        let migrations: &[Migration] = &[
+            |connection| connection.execute_batch("CREATE INDEX users_v ON users (v)"),
+            |connection| {
+                Table::existing("users", connection)
+                    .update("jimmy", "def")
+                    .map(|_| ())
+            },
+        ];
+
+        let owned = Table::open_and_migrate(&path, "users", migrations).unwrap();
+        assert_eq!(2, user_version(owned.connection()));
+        assert_eq!("def", owned.table().get::<String>("jimmy").unwrap());
+        owned.table().update("jimmy", "ghi").unwrap();
+        drop(owned);
+
+        // Already up to date, nothing is rerun
+        let owned = Table::open_and_migrate(&path, "users", migrations).unwrap();
+        assert_eq!(2, user_version(owned.connection()));
+        assert_eq!("ghi", owned.table().get::<String>("jimmy").unwrap());
+        drop(owned);
+
+        // Newer than the known migrations
+        assert!(Table::open_and_migrate(&path, "users", &migrations[..1]).is_err());
+    }
+
+    #[test]
+    fn test_open_and_migrate_failure_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open_and_migrate_failure.sqlite");
+
+        let migrations: &[Migration] = &[
+            |connection| {
+                Table::existing("users", connection)
+                    .set("jimmy", "abc")
+                    .map(|_| ())
+            },
+            |connection| connection.execute_batch("ALTER TABLE missing ADD COLUMN x"),
+        ];
+
+        assert!(Table::open_and_migrate(&path, "users", migrations).is_err());
+
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(0, user_version(&connection));
+        assert!(Table::existing("users", &connection).keys().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{DatabaseTable, Table};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{types::FromSql, Error, ToSql};
+
+type SqlitePool = Pool<SqliteConnectionManager>;
+
+/// A table handle that routes reads and writes to separate pools
+///
+/// `get`, `keys` and `count` check a connection out of the read pool while
+/// `set`, `update` and `remove` use the write pool. This suits deployments
+/// where reads go to a replica.
+///
+/// With both pools pointing at a single SQLite file this is mostly a
+/// concurrency hint: every connection still sees the same file, but a
+/// small write pool (usually a size of 1) keeps writers from contending
+/// with each other while readers are free to run in parallel under WAL.
+///
+/// Cloning is cheap, pools are reference counted.
+#[derive(Debug, Clone)]
+pub struct ReadWriteTable {
+    name: String,
+    read: SqlitePool,
+    write: SqlitePool,
+}
+
+impl ReadWriteTable {
+    /// Creates a new table handle from a read and a write pool
+    ///
+    /// Like `Table::existing` this doesn't run a `CREATE` command, the
+    /// table is expected to exist already.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{ReadWriteTable, Table};
+    /// use r2d2::Pool;
+    /// use r2d2_sqlite::SqliteConnectionManager;
+    ///
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("users.sqlite");
+    /// let write = Pool::builder().max_size(1).build(SqliteConnectionManager::file(&path)).unwrap();
+    /// let read = Pool::new(SqliteConnectionManager::file(&path)).unwrap();
+    /// Table::new("users").create(&write.get().unwrap())?;
+    ///
+    /// let table = ReadWriteTable::new("users", read, write);
+    ///
+    /// table.set("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn new(name: &str, read: SqlitePool, write: SqlitePool) -> Self {
+        ReadWriteTable {
+            name: name.to_owned(),
+            read,
+            write,
+        }
+    }
+
+    /// Runs `f` against the table using a connection from the read pool
+    pub fn with_read<R>(
+        &self,
+        f: impl FnOnce(&DatabaseTable) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let connection = checkout(&self.read)?;
+
+        f(&Table::existing(&self.name, &connection))
+    }
+
+    /// Runs `f` against the table using a connection from the write pool
+    pub fn with_write<R>(
+        &self,
+        f: impl FnOnce(&DatabaseTable) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let connection = checkout(&self.write)?;
+
+        f(&Table::existing(&self.name, &connection))
+    }
+
+    /// Inserts some data into the table, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.with_write(|table| table.set(key, value))
+    }
+
+    /// Update a value in the table, see `DatabaseTable::update`
+    pub fn update<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.with_write(|table| table.update(key, value))
+    }
+
+    /// Remove a value from the table, see `DatabaseTable::remove`
+    pub fn remove(&self, key: &str) -> Result<usize, Error> {
+        self.with_write(|table| table.remove(key))
+    }
+
+    /// Get a value in the database by key, see `DatabaseTable::get`
+    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        self.with_read(|table| table.get(key))
+    }
+
+    /// Get every key in the table, see `DatabaseTable::keys`
+    pub fn keys(&self) -> Result<Vec<String>, Error> {
+        self.with_read(|table| table.keys())
+    }
+
+    /// Count the rows in the table, see `DatabaseTable::count`
+    pub fn count(&self) -> Result<usize, Error> {
+        self.with_read(|table| table.count())
+    }
+}
+
+/// Checks a connection out of `pool`, surfacing pool timeouts as a busy
+/// SQLite error.
+fn checkout(pool: &SqlitePool) -> Result<PooledConnection<SqliteConnectionManager>, Error> {
+    pool.get().map_err(|e| {
+        Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            Some(e.to_string()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::OptionalExtension;
+
+    #[test]
+    fn test_read_write_pools() {
+        // Two pools over the same shared-cache in-memory database
+        let uri = "file:adrodb_read_write?mode=memory&cache=shared";
+        let write = Pool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::file(uri))
+            .unwrap();
+        let read = Pool::builder()
+            .max_size(2)
+            .build(SqliteConnectionManager::file(uri))
+            .unwrap();
+        Table::new("users").create(&write.get().unwrap()).unwrap();
+
+        let table = ReadWriteTable::new("users", read, write);
+
+        table.set("jimmy", "abc").unwrap();
+        table.set("bob", "def").unwrap();
+        table.update("bob", "ghi").unwrap();
+
+        assert_eq!("abc", table.get::<String>("jimmy").unwrap());
+        assert_eq!("ghi", table.get::<String>("bob").unwrap());
+        assert_eq!(vec!["bob", "jimmy"], table.keys().unwrap());
+        assert_eq!(2, table.count().unwrap());
+
+        table.remove("jimmy").unwrap();
+
+        assert_eq!(None, table.get::<String>("jimmy").optional().unwrap());
+        assert_eq!(1, table.count().unwrap());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{DatabaseTable, Table};
+use rusqlite::{types::FromSql, Connection, Error, Result, ToSql};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A table handle that can be shared across threads
+///
+/// Wraps a single connection in an `Arc<Mutex<Connection>>` so that many
+/// threads (or actix workers via `web::Data`) can use the same connection
+/// instead of opening a new one per request. Each operation locks the
+/// connection, runs and releases the lock when done.
+///
+/// Cloning a `SharedTable` is cheap and every clone refers to the same
+/// connection.
+///
+/// Because every operation is serialized through the one connection, there
+/// are no `SQLITE_BUSY` errors between clones and no lost writes. Group
+/// read-modify-write steps with `with` so other threads can't interleave
+/// between them.
+#[derive(Debug, Clone)]
+pub struct SharedTable {
+    name: String,
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SharedTable {
+    /// Creates a new shared table from an owned connection
+    ///
+    /// Like `Table::existing` this doesn't run a `CREATE` command, the
+    /// table is expected to exist already.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{SharedTable, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// Table::new("users").create(&connection)?;
+    ///
+    /// let shared = SharedTable::new("users", connection);
+    ///
+    /// shared.set("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn new(name: &str, connection: Connection) -> Self {
+        SharedTable::from_shared(name, Arc::new(Mutex::new(connection)))
+    }
+
+    /// Opens a database, creates the table and shares the connection
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::SharedTable;
+    ///
+    /// let shared = SharedTable::open(":memory:", "users")?;
+    ///
+    /// shared.set("jimmy", "abc@abc.com")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P, name: &str) -> Result<Self, Error> {
+        let connection = Connection::open(path)?;
+        Table::new(name).create(&connection)?;
+
+        Ok(SharedTable::new(name, connection))
+    }
+
+    /// Creates a new shared table from an already shared connection
+    ///
+    /// Useful when several tables need to share the same connection.
+    pub fn from_shared(name: &str, connection: Arc<Mutex<Connection>>) -> Self {
+        SharedTable {
+            name: name.to_owned(),
+            connection,
+        }
+    }
+
+    /// Locks the connection and runs `f` against the table
+    ///
+    /// The lock is held for the duration of `f` so several operations
+    /// can be grouped together without other threads interleaving. A
+    /// poisoned lock is recovered as the connection itself is still
+    /// usable.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::{SharedTable, Table};
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// Table::new("users").create(&connection)?;
+    /// let shared = SharedTable::new("users", connection);
+    ///
+    /// let value = shared.with(|table| {
+    ///     table.set("jimmy", "abc")?;
+    ///     table.get::<String>("jimmy")
+    /// })?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn with<R>(&self, f: impl FnOnce(&DatabaseTable) -> Result<R, Error>) -> Result<R, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let table = Table::existing(&self.name, &connection);
+
+        f(&table)
+    }
+
+    /// Inserts some data into the table, see `DatabaseTable::set`
+    pub fn set<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.with(|table| table.set(key, value))
+    }
+
+    /// Get a value in the database by key, see `DatabaseTable::get`
+    pub fn get<T: FromSql>(&self, key: &str) -> Result<T, Error> {
+        self.with(|table| table.get(key))
+    }
+
+    /// Removes data by the key, see `DatabaseTable::remove`
+    pub fn remove(&self, key: &str) -> Result<usize, Error> {
+        self.with(|table| table.remove(key))
+    }
+
+    /// Updates some data in the table, see `DatabaseTable::update`
+    pub fn update<T: ToSql + ?Sized>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.with(|table| table.update(key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_shared_table_across_threads() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+        let shared = SharedTable::new("users", conn);
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.set(&format!("user{}", i), &i).unwrap())
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for i in 0..8 {
+            let value = shared.get::<i32>(&format!("user{}", i)).unwrap();
+            assert_eq!(i, value);
+        }
+    }
+
+    #[test]
+    fn test_shared_table_with() {
+        let conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+        let shared = SharedTable::new("users", conn);
+
+        let result = shared.with(|table| {
+            table.set("jimmy", "abc")?;
+            table.update("jimmy", "def")?;
+            table.get::<String>("jimmy")
+        });
+
+        assert_eq!("def", result.unwrap());
+    }
+}
@@ -0,0 +1,384 @@
+use crate::DatabaseTable;
+use rusqlite::{Connection, Error, Result, Transaction, TransactionBehavior};
+use std::ops::Deref;
+
+/// Name of the savepoints opened by `Scope`
+const SAVEPOINT: &str = "adrodb";
+
+/// A transaction that nests inside one already open on the connection
+///
+/// Methods that need atomicity, such as `set_many`, open a `Scope` rather
+/// than a `Transaction`. When the connection is in autocommit mode it
+/// begins a transaction, otherwise (e.g. through `Table::existing` with a
+/// `Transaction`, or a `TransactionGuard`) it opens a savepoint, so the
+/// same method works inside and outside a transaction. Rolls back when
+/// dropped without `commit`.
+#[derive(Debug)]
+pub(crate) struct Scope<'c> {
+    connection: &'c Connection,
+    nested: bool,
+    finished: bool,
+}
+
+impl<'c> Scope<'c> {
+    /// Opens a deferred scope
+    pub(crate) fn begin(connection: &'c Connection) -> Result<Self, Error> {
+        Scope::begin_with(connection, TransactionBehavior::Deferred)
+    }
+
+    /// Opens a scope, `behavior` applies when it begins a transaction
+    pub(crate) fn begin_with(
+        connection: &'c Connection,
+        behavior: TransactionBehavior,
+    ) -> Result<Self, Error> {
+        let nested = !connection.is_autocommit();
+        let sql = if nested {
+            format!("SAVEPOINT {}", SAVEPOINT)
+        } else {
+            match behavior {
+                TransactionBehavior::Immediate => "BEGIN IMMEDIATE".to_owned(),
+                TransactionBehavior::Exclusive => "BEGIN EXCLUSIVE".to_owned(),
+                _ => "BEGIN DEFERRED".to_owned(),
+            }
+        };
+        connection.execute_batch(&sql)?;
+
+        Ok(Scope {
+            connection,
+            nested,
+            finished: false,
+        })
+    }
+
+    /// Commits the transaction or releases the savepoint
+    pub(crate) fn commit(mut self) -> Result<(), Error> {
+        self.finished = true;
+
+        if self.nested {
+            self.connection
+                .execute_batch(&format!("RELEASE {}", SAVEPOINT))
+        } else {
+            self.connection.execute_batch("COMMIT")
+        }
+    }
+}
+
+impl Deref for Scope<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+    }
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let _ = if self.nested {
+            self.connection
+                .execute_batch(&format!("ROLLBACK TO {}; RELEASE {}", SAVEPOINT, SAVEPOINT))
+        } else {
+            self.connection.execute_batch("ROLLBACK")
+        };
+    }
+}
+
+/// A transaction over a table that commits when dropped
+///
+/// Returned by `DatabaseTable::begin`. Operations performed through the
+/// guard (it dereferences to the `DatabaseTable`) are held in the open
+/// transaction and committed together when the guard goes out of scope,
+/// unless `rollback` is called. If the thread is panicking the transaction
+/// is rolled back instead.
+///
+/// A failed commit can't be returned from `Drop`, it is only logged when
+/// the `tracing` feature is enabled, so call `commit` explicitly wherever
+/// the error matters.
+///
+/// Methods which open their own transaction, such as `set_many` or
+/// `map_value`, run in a savepoint when used through the guard, so they
+/// stay atomic and are committed or rolled back with the rest of it.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::Table;
+/// use rusqlite::Connection;
+///
+/// let connection = Connection::open_in_memory()?;
+/// # Table::new("users").create(&connection)?;
+/// let table = Table::existing("users", &connection);
+///
+/// {
+///     let guard = table.begin()?;
+///     guard.set("jimmy", "abc")?;
+///     guard.set("bob", "def")?;
+/// } // committed here
+///
+/// assert_eq!("abc", table.get::<String>("jimmy")?);
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub struct TransactionGuard<'a> {
+    table: DatabaseTable<'a>,
+    transaction: Option<Transaction<'a>>,
+}
+
+impl<'a> TransactionGuard<'a> {
+    /// Commits the transaction, surfacing any error
+    pub fn commit(mut self) -> Result<(), Error> {
+        match self.transaction.take() {
+            Some(transaction) => transaction.commit(),
+            None => Ok(()),
+        }
+    }
+
+    /// Rolls back every operation performed through the guard
+    pub fn rollback(mut self) -> Result<(), Error> {
+        match self.transaction.take() {
+            Some(transaction) => transaction.rollback(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Deref for TransactionGuard<'a> {
+    type Target = DatabaseTable<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+impl Drop for TransactionGuard<'_> {
+    fn drop(&mut self) {
+        let transaction = match self.transaction.take() {
+            Some(transaction) => transaction,
+            None => return,
+        };
+
+        if std::thread::panicking() {
+            let _ = transaction.rollback();
+            return;
+        }
+
+        #[allow(unused_variables)]
+        if let Err(error) = transaction.commit() {
+            #[cfg(feature = "tracing")]
+            tracing::error!(table = self.table.name, %error, "failed to commit transaction on drop");
+        }
+    }
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Starts a transaction that commits when the returned guard is dropped
+    ///
+    /// The transaction is deferred, see `transaction_with` to choose when
+    /// locks are taken and `TransactionGuard` for how it completes.
+    pub fn begin(&self) -> Result<TransactionGuard<'a>, Error> {
+        self.transaction_with(TransactionBehavior::Deferred)
+    }
+
+    /// Starts a transaction with the given behavior
+    ///
+    /// `TransactionBehavior::Immediate` takes the write lock straight away
+    /// rather than on the first write, so concurrent writers wait (or get
+    /// `SQLITE_BUSY`) at `BEGIN` instead of deadlocking when upgrading a
+    /// read lock part way through. Otherwise the same as `begin`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::{Connection, TransactionBehavior};
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let guard = table.transaction_with(TransactionBehavior::Immediate)?;
+    /// guard.set("jimmy", "abc")?;
+    /// guard.commit()?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn transaction_with(
+        &self,
+        behavior: TransactionBehavior,
+    ) -> Result<TransactionGuard<'a>, Error> {
+        let transaction = Transaction::new_unchecked(self.connection, behavior)?;
+
+        Ok(TransactionGuard {
+            table: self.clone(),
+            transaction: Some(transaction),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use rusqlite::{Connection, Error, OptionalExtension, TransactionBehavior};
+
+    fn add_user(connection: &Connection, name: &str) -> Result<usize, Error> {
+        Table::existing("users", connection).set(name, "active")
+    }
+
+    #[test]
+    fn test_commit_on_drop() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        {
+            let guard = db.begin().unwrap();
+            guard.set("jimmy", "abc").unwrap();
+            guard.set("bob", "def").unwrap();
+            assert!(!conn.is_autocommit());
+        }
+
+        assert!(conn.is_autocommit());
+        assert_eq!(vec!["bob", "jimmy"], db.keys().unwrap());
+    }
+
+    #[test]
+    fn test_explicit_commit() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let guard = db.begin().unwrap();
+        guard.set("jimmy", "abc").unwrap();
+        guard.commit().unwrap();
+
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        db.set("jimmy", "abc").unwrap();
+
+        let guard = db.begin().unwrap();
+        guard.set("bob", "def").unwrap();
+        guard.update("jimmy", "ghi").unwrap();
+        guard.rollback().unwrap();
+
+        assert_eq!(None, db.get::<String>("bob").optional().unwrap());
+        assert_eq!("abc", db.get::<String>("jimmy").unwrap());
+    }
+
+    #[test]
+    fn test_nested_methods_through_guard() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let guard = db.begin().unwrap();
+        guard.set_many(&[("jimmy", 1), ("bob", 2)]).unwrap();
+        guard.map_value::<i64>("jimmy", |v| v + 10).unwrap();
+        guard.rollback().unwrap();
+
+        assert!(conn.is_autocommit());
+        assert!(db.keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_transaction_with_immediate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transaction_with_immediate.sqlite");
+
+        let conn = Connection::open(&path).unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let other = Connection::open(&path).unwrap();
+        other.busy_timeout(std::time::Duration::ZERO).unwrap();
+
+        let guard = db.transaction_with(TransactionBehavior::Immediate).unwrap();
+
+        // The write lock is held before anything is written
+        assert!(Table::existing("users", &other).set("bob", "def").is_err());
+
+        guard.set("jimmy", "abc").unwrap();
+        guard.commit().unwrap();
+
+        assert_eq!(
+            "abc",
+            Table::existing("users", &other)
+                .get::<String>("jimmy")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_existing_in_transaction() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+
+        add_user(&conn, "jimmy").unwrap();
+
+        let transaction = conn.transaction().unwrap();
+        add_user(&transaction, "bob").unwrap();
+        transaction.rollback().unwrap();
+
+        let transaction = conn.transaction().unwrap();
+        add_user(&transaction, "sally").unwrap();
+        transaction.commit().unwrap();
+
+        let users = Table::existing("users", &conn);
+        assert_eq!(vec!["jimmy", "sally"], users.keys().unwrap());
+        assert_eq!(None, users.get::<String>("bob").optional().unwrap());
+    }
+
+    #[test]
+    fn test_set_many_in_transaction() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+
+        let transaction = conn.transaction().unwrap();
+        let users = Table::existing("users", &transaction);
+        users.set_many(&[("jimmy", 1), ("bob", 2)]).unwrap();
+
+        // A failing batch only undoes its own writes
+        assert!(users.set_many(&[("sally", 3), ("jimmy", 4)]).is_err());
+        assert_eq!(vec!["bob", "jimmy"], users.keys().unwrap());
+        transaction.rollback().unwrap();
+
+        assert!(Table::existing("users", &conn).keys().unwrap().is_empty());
+
+        let transaction = conn.transaction().unwrap();
+        Table::existing("users", &transaction)
+            .set_many(&[("jimmy", 1), ("bob", 2)])
+            .unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(
+            vec!["bob", "jimmy"],
+            Table::existing("users", &conn).keys().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_existing_in_savepoint() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        Table::new("users").create(&conn).unwrap();
+
+        let mut transaction = conn.transaction().unwrap();
+        add_user(&transaction, "jimmy").unwrap();
+
+        let mut savepoint = transaction.savepoint().unwrap();
+        add_user(&savepoint, "bob").unwrap();
+        savepoint.rollback().unwrap();
+        drop(savepoint);
+
+        transaction.commit().unwrap();
+
+        assert_eq!(
+            vec!["jimmy"],
+            Table::existing("users", &conn).keys().unwrap()
+        );
+    }
+}
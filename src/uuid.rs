@@ -0,0 +1,75 @@
+use crate::DatabaseTable;
+use ::uuid::Uuid;
+use rusqlite::{Error, Result};
+
+impl<'a> DatabaseTable<'a> {
+    /// Inserts a `Uuid` value into the table
+    ///
+    /// Values are stored as a 16-byte BLOB using rusqlite's `uuid` support,
+    /// which is half the size of the hyphenated text form. With the `uuid`
+    /// feature enabled `set` and `get` also accept `Uuid` directly.
+    ///
+    /// Keys are text, a `Uuid` key can be used through its hyphenated form
+    /// e.g. `id.hyphenated().to_string()`. Requires the `uuid` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    /// use uuid::Uuid;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// let id = Uuid::new_v4();
+    /// table.set_uuid("jimmy", &id)?;
+    ///
+    /// assert_eq!(id, table.get_uuid("jimmy")?);
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn set_uuid(&self, key: &str, value: &Uuid) -> Result<usize, Error> {
+        self.set(key, value)
+    }
+
+    /// Get a `Uuid` value by key
+    ///
+    /// Reads a value written by `set_uuid`. Requires the `uuid` feature.
+    pub fn get_uuid(&self, key: &str) -> Result<Uuid, Error> {
+        self.get::<Uuid>(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use ::uuid::Uuid;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let id = Uuid::new_v4();
+        db.set_uuid("jimmy", &id).unwrap();
+
+        assert_eq!(id, db.get_uuid("jimmy").unwrap());
+        assert_eq!(16, db.get::<Vec<u8>>("jimmy").unwrap().len());
+    }
+
+    #[test]
+    fn test_uuid_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+
+        let id = Uuid::new_v4();
+        db.set(&id.hyphenated().to_string(), "jimmy").unwrap();
+
+        let result = db.get::<String>(&id.hyphenated().to_string()).unwrap();
+        assert_eq!("jimmy", result);
+    }
+}
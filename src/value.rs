@@ -0,0 +1,162 @@
+use crate::{DatabaseTable, VALUE_COLUMN};
+use rusqlite::{
+    types::{FromSql, FromSqlError, Value, ValueRef},
+    Error, Result, ToSql,
+};
+
+/// Converts a type to and from a value adrodb can store
+///
+/// A lightweight alternative to derive macros for storing your own types,
+/// e.g. enums by their discriminant, through `set_value` & `get_value`.
+///
+/// ### Example
+///
+/// ```
+/// use adrodb::{AsKvValue, Table};
+/// use rusqlite::Connection;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Status {
+///     Active = 1,
+///     Banned = 2,
+/// }
+///
+/// impl AsKvValue for Status {
+///     type Value = i64;
+///
+///     fn to_kv_value(&self) -> i64 {
+///         match self {
+///             Status::Active => 1,
+///             Status::Banned => 2,
+///         }
+///     }
+///
+///     fn from_kv_value(value: i64) -> Option<Self> {
+///         match value {
+///             1 => Some(Status::Active),
+///             2 => Some(Status::Banned),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let connection = Connection::open_in_memory()?;
+/// # Table::new("users").create(&connection)?;
+/// let table = Table::existing("users", &connection);
+///
+/// table.set_value("jimmy", &Status::Banned)?;
+///
+/// assert_eq!(Status::Banned, table.get_value::<Status>("jimmy")?);
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub trait AsKvValue: Sized {
+    /// The type actually stored in the table
+    type Value: ToSql + FromSql;
+
+    /// Converts into the stored value
+    fn to_kv_value(&self) -> Self::Value;
+
+    /// Converts from a stored value, `None` if it isn't valid for the type
+    fn from_kv_value(value: Self::Value) -> Option<Self>;
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Inserts a value using its `AsKvValue` conversion
+    pub fn set_value<T: AsKvValue>(&self, key: &str, value: &T) -> Result<usize, Error> {
+        self.set(key, &value.to_kv_value())
+    }
+
+    /// Get a value by key using its `AsKvValue` conversion
+    ///
+    /// Errors with `Error::FromSqlConversionFailure` if the stored value
+    /// isn't valid for `T`.
+    pub fn get_value<T: AsKvValue>(&self, key: &str) -> Result<T, Error> {
+        let value = self.get::<Value>(key)?;
+
+        T::from_kv_value(from_value(&value)?).ok_or_else(|| {
+            Error::FromSqlConversionFailure(
+                0,
+                value.data_type(),
+                format!("invalid value for {}", std::any::type_name::<T>()).into(),
+            )
+        })
+    }
+}
+
+/// Converts an owned value into `T` the same way reading a row would
+pub(crate) fn from_value<T: FromSql>(value: &Value) -> Result<T, Error> {
+    let value = ValueRef::from(value);
+
+    T::column_result(value).map_err(|err| match err {
+        FromSqlError::InvalidType => {
+            Error::InvalidColumnType(0, VALUE_COLUMN.to_owned(), value.data_type())
+        }
+        err => Error::FromSqlConversionFailure(0, value.data_type(), Box::new(err)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+
+    #[derive(Debug, PartialEq)]
+    enum Light {
+        Red,
+        Amber,
+        Green,
+    }
+
+    impl AsKvValue for Light {
+        type Value = i64;
+
+        fn to_kv_value(&self) -> i64 {
+            match self {
+                Light::Red => 0,
+                Light::Amber => 1,
+                Light::Green => 2,
+            }
+        }
+
+        fn from_kv_value(value: i64) -> Option<Self> {
+            match value {
+                0 => Some(Light::Red),
+                1 => Some(Light::Amber),
+                2 => Some(Light::Green),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_value_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("lights");
+        let db = table.create(&conn).unwrap();
+
+        db.set_value("main", &Light::Amber).unwrap();
+
+        assert_eq!(Light::Amber, db.get_value::<Light>("main").unwrap());
+        assert_eq!(1, db.get::<i64>("main").unwrap());
+    }
+
+    #[test]
+    fn test_value_invalid() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("lights");
+        let db = table.create(&conn).unwrap();
+
+        db.set("unknown", &7).unwrap();
+        db.set("text", "red").unwrap();
+
+        assert!(matches!(
+            db.get_value::<Light>("unknown"),
+            Err(Error::FromSqlConversionFailure(..))
+        ));
+        assert!(matches!(
+            db.get_value::<Light>("text"),
+            Err(Error::InvalidColumnType(..))
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{DatabaseTable, KEY_COLUMN, VALUE_COLUMN};
+use rusqlite::{functions::FunctionFlags, Error, Result};
+use std::panic::AssertUnwindSafe;
+
+/// The kind of change reported to a `watch` callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    const ALL: [Operation; 3] = [Operation::Insert, Operation::Update, Operation::Delete];
+
+    /// The trigger event & the row holding the key
+    fn trigger(&self) -> (&'static str, &'static str) {
+        match self {
+            Operation::Insert => ("INSERT", "new"),
+            Operation::Update => ("UPDATE", "new"),
+            Operation::Delete => ("DELETE", "old"),
+        }
+    }
+}
+
+/// Name of the SQL function & prefix of the triggers `watch` creates
+fn watch_name(table: &str) -> String {
+    format!("adrodb_watch_{}", table)
+}
+
+impl<'a> DatabaseTable<'a> {
+    /// Registers a callback invoked whenever a row in the table changes
+    ///
+    /// Calls `callback` with the operation and the key of the affected row
+    /// after every insert, update or delete on this table made through the
+    /// same connection. Deletes report the key the row had. Updates are
+    /// only reported when the key or value changes, so reads recorded by
+    /// `track_access` don't notify.
+    ///
+    /// This deliberately isn't built on SQLite's update hook: the hook
+    /// only reports a rowid, which can't be looked up while the hook runs,
+    /// nor at all once the row is deleted. Instead `watch` creates `TEMP`
+    /// triggers, private to the connection, which pass the key to the
+    /// callback through a SQL function. Requires the `watch` feature.
+    ///
+    /// There are a few limitations to be aware of:
+    ///
+    /// - The callback runs while the statement is executing, it must not
+    ///   use the connection that triggered it.
+    /// - Changes are reported as they're made, including ones later rolled
+    ///   back.
+    /// - Watching the table again replaces the previous callback.
+    /// - Changes made through other connections aren't reported.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// use adrodb::Table;
+    /// use rusqlite::Connection;
+    ///
+    /// let connection = Connection::open_in_memory()?;
+    /// # Table::new("users").create(&connection)?;
+    /// let table = Table::existing("users", &connection);
+    ///
+    /// table.watch(|operation, key| println!("{:?} {}", operation, key))?;
+    ///
+    /// table.set("jimmy", "abc")?;
+    /// # Ok::<(), rusqlite::Error>(())
+    /// ```
+    pub fn watch(&self, callback: impl Fn(Operation, &str) + Send + 'static) -> Result<(), Error> {
+        let name = watch_name(self.name);
+        let callback = AssertUnwindSafe(callback);
+
+        self.connection.create_scalar_function(
+            name.as_str(),
+            2,
+            FunctionFlags::SQLITE_UTF8,
+            move |ctx| {
+                let operation = Operation::ALL[ctx.get::<usize>(0)?];
+                (*callback)(operation, &ctx.get::<String>(1)?);
+                Ok(None::<i64>)
+            },
+        )?;
+
+        for (index, operation) in Operation::ALL.iter().enumerate() {
+            let (event, row) = operation.trigger();
+            // Only key & value changes, not bookkeeping such as `track_access`
+            let columns = match operation {
+                Operation::Update => format!(" OF {}, {}", KEY_COLUMN, VALUE_COLUMN),
+                _ => String::new(),
+            };
+            self.connection.execute_batch(&format!(
+                "CREATE TEMP TRIGGER IF NOT EXISTS {name}_{event} AFTER {event}{columns} ON main.{table} BEGIN
+                    SELECT {name}({index}, {row}.{k});
+                END;",
+                name = name,
+                event = event.to_ascii_lowercase(),
+                columns = columns,
+                table = self.name,
+                index = index,
+                row = row,
+                k = KEY_COLUMN
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the callback registered with `watch`
+    pub fn unwatch(&self) -> Result<(), Error> {
+        let name = watch_name(self.name);
+
+        for operation in Operation::ALL {
+            let (event, _) = operation.trigger();
+            self.connection.execute_batch(&format!(
+                "DROP TRIGGER IF EXISTS temp.{}_{}",
+                name,
+                event.to_ascii_lowercase()
+            ))?;
+        }
+
+        self.connection.remove_function(&name, 2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Table;
+    use rusqlite::Connection;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_watch() {
+        let conn = Connection::open_in_memory().unwrap();
+        let table = Table::new("users");
+        let db = table.create(&conn).unwrap();
+        let other = Table::new("other");
+        let other = other.create(&conn).unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+
+        db.watch(move |operation, key| recorded.lock().unwrap().push((operation, key.to_owned())))
+            .unwrap();
+
+        db.set("jimmy", "abc").unwrap();
+        db.update("jimmy", "def").unwrap();
+        other.set("c", "d").unwrap();
+        db.remove("jimmy").unwrap();
+
+        db.unwatch().unwrap();
+        db.set("bob", "abc").unwrap();
+
+        assert_eq!(
+            vec![
+                (Operation::Insert, "jimmy".to_owned()),
+                (Operation::Update, "jimmy".to_owned()),
+                (Operation::Delete, "jimmy".to_owned()),
+            ],
+            *changes.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_watch_tables_separately() {
+        let conn = Connection::open_in_memory().unwrap();
+        let users = Table::new("users");
+        let users = users.create(&conn).unwrap();
+        let posts = Table::new("posts");
+        let posts = posts.create(&conn).unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        users
+            .watch(move |_, key| recorded.lock().unwrap().push(format!("users:{}", key)))
+            .unwrap();
+        let recorded = changes.clone();
+        posts
+            .watch(move |_, key| recorded.lock().unwrap().push(format!("posts:{}", key)))
+            .unwrap();
+
+        users.set("jimmy", "abc").unwrap();
+        posts.set("first", "hello").unwrap();
+        users.remove_many(&["jimmy"]).unwrap();
+
+        assert_eq!(
+            vec!["users:jimmy", "posts:first", "users:jimmy"],
+            *changes.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "actix")]
+
+use actix_web::{get, http::StatusCode, post, test, web, App, HttpResponse};
+use adrodb::SharedTable;
+
+#[post("/{key}/{value}")]
+async fn insert(table: SharedTable, path: web::Path<(String, String)>) -> HttpResponse {
+    let (key, value) = path.into_inner();
+
+    match table.set(&key, &value) {
+        Ok(_) => HttpResponse::Created().finish(),
+        Err(_) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[get("/{key}")]
+async fn get(table: SharedTable, key: web::Path<String>) -> HttpResponse {
+    match table.get::<String>(&key) {
+        Ok(value) => HttpResponse::Ok().body(value),
+        Err(_) => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[actix_web::test]
+async fn test_post_and_get() {
+    let table = SharedTable::open(":memory:", "users").unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(table.data())
+            .service(insert)
+            .service(get),
+    )
+    .await;
+
+    let request = test::TestRequest::post().uri("/jimmy/abc").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(StatusCode::CREATED, response.status());
+
+    let request = test::TestRequest::get().uri("/jimmy").to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    assert_eq!("abc", body);
+
+    let request = test::TestRequest::get().uri("/bob").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    assert_eq!("abc", table.get::<String>("jimmy").unwrap());
+}
+
+#[actix_web::test]
+async fn test_missing_app_data() {
+    let app = test::init_service(App::new().service(get)).await;
+
+    let request = test::TestRequest::get().uri("/jimmy").to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+}
@@ -0,0 +1,103 @@
+use adrodb::{SharedTable, Table};
+use rusqlite::{Connection, ErrorCode};
+use std::thread;
+
+const THREADS: usize = 8;
+const OPERATIONS: usize = 100;
+
+/// The pattern `SharedTable` replaces: each request opens its own
+/// connection, so two requests reading then writing the same row contend
+/// for the database and the later writer fails with `SQLITE_BUSY`.
+#[test]
+fn test_per_request_connections_contend() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("per_request.sqlite");
+    let setup = Connection::open(&path).unwrap();
+    setup.pragma_update(None, "journal_mode", "WAL").unwrap();
+    Table::new("counters")
+        .create(&setup)
+        .unwrap()
+        .set("visits", &0)
+        .unwrap();
+
+    let first = Connection::open(&path).unwrap();
+    let first = first.unchecked_transaction().unwrap();
+    let second = Connection::open(&path).unwrap();
+    let second = second.unchecked_transaction().unwrap();
+    let first_table = Table::existing("counters", &first);
+    let second_table = Table::existing("counters", &second);
+
+    let first_visits = first_table.get::<i64>("visits").unwrap();
+    let second_visits = second_table.get::<i64>("visits").unwrap();
+    first_table.update("visits", &(first_visits + 1)).unwrap();
+    first.commit().unwrap();
+
+    let error = second_table
+        .update("visits", &(second_visits + 1))
+        .unwrap_err();
+    assert_eq!(Some(ErrorCode::DatabaseBusy), error.sqlite_error_code());
+}
+
+#[test]
+fn test_concurrent_inserts_and_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("concurrent_inserts_and_reads.sqlite");
+    let shared = SharedTable::open(&path, "users").unwrap();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let shared = shared.clone();
+
+            thread::spawn(move || {
+                for i in 0..OPERATIONS {
+                    let key = format!("{}:{}", t, i);
+
+                    shared.set(&key, &(i as i64)).unwrap();
+                    assert_eq!(i as i64, shared.get::<i64>(&key).unwrap());
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let keys = shared.with(|table| table.keys()).unwrap();
+    assert_eq!(THREADS * OPERATIONS, keys.len());
+}
+
+/// Unlike `test_per_request_connections_contend`, reading then writing
+/// through the shared connection neither fails nor loses increments.
+#[test]
+fn test_concurrent_increments_are_not_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("concurrent_increments.sqlite");
+    let shared = SharedTable::open(&path, "counters").unwrap();
+    shared.set("visits", &0).unwrap();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let shared = shared.clone();
+
+            thread::spawn(move || {
+                for _ in 0..OPERATIONS {
+                    shared
+                        .with(|table| {
+                            let visits = table.get::<i64>("visits")?;
+                            thread::yield_now();
+                            table.update("visits", &(visits + 1))
+                        })
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let visits = shared.get::<i64>("visits").unwrap();
+    assert_eq!((THREADS * OPERATIONS) as i64, visits);
+}